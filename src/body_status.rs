@@ -0,0 +1,70 @@
+use std::str::FromStr;
+
+use scraper::{Html, Selector};
+
+#[derive(Debug, Clone)]
+pub struct BodyStatusSelector {
+    selector: Selector,
+    attribute: Option<String>,
+}
+
+impl FromStr for BodyStatusSelector {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (selector, attribute) = match value.rsplit_once('@') {
+            Some((selector, attribute)) => (selector, Some(attribute.to_owned())),
+            None => (value, None),
+        };
+        let selector = Selector::parse(selector).map_err(|err| err.to_string())?;
+
+        Ok(BodyStatusSelector {
+            selector,
+            attribute,
+        })
+    }
+}
+
+impl BodyStatusSelector {
+    pub fn status(&self, document: &Html) -> Option<u16> {
+        let element = document.select(&self.selector).next()?;
+        let value = match &self.attribute {
+            Some(attribute) => element.attr(attribute)?.to_owned(),
+            None => element.text().collect(),
+        };
+
+        value
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+            .filter(|status| (100..=599).contains(status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_from_body() {
+        let document = Html::parse_document(
+            r#"<html><head><meta http-equiv="Status" content="404 Not Found"></head>
+            <body><span class="code">503</span><span class="bad">oops</span></body></html>"#,
+        );
+
+        let meta = BodyStatusSelector::from_str(r#"meta[http-equiv="Status"]@content"#).unwrap();
+        assert_eq!(meta.status(&document), Some(404));
+
+        let text = BodyStatusSelector::from_str("span.code").unwrap();
+        assert_eq!(text.status(&document), Some(503));
+
+        let not_a_status = BodyStatusSelector::from_str("span.bad").unwrap();
+        assert_eq!(not_a_status.status(&document), None);
+
+        let missing = BodyStatusSelector::from_str("meta[name=status]@content").unwrap();
+        assert_eq!(missing.status(&document), None);
+
+        assert!(BodyStatusSelector::from_str("[[[@content").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    encoding::fnv1a_hash,
+    journal::{JournalEntry, JournalHistory},
+    refresh::now_secs,
+};
+
+const LATEST: &str = "checkpoint";
+const PREVIOUS: &str = "checkpoint.prev";
+const TEMPORARY: &str = "checkpoint.tmp";
+
+pub struct Checkpoint {
+    pub history: JournalHistory,
+    pub journal_offset: u64,
+}
+
+impl Checkpoint {
+    pub fn write(&self, directory: &Path) -> Result<(), String> {
+        let content = self.serialize();
+        let temporary = directory.join(TEMPORARY);
+
+        let mut file = fs::File::create(&temporary).map_err(|err| err.to_string())?;
+        file.write_all(content.as_bytes())
+            .map_err(|err| err.to_string())?;
+        file.sync_all().map_err(|err| err.to_string())?;
+
+        let latest = directory.join(LATEST);
+        if latest.exists() {
+            fs::rename(&latest, directory.join(PREVIOUS)).map_err(|err| err.to_string())?;
+        }
+        fs::rename(&temporary, &latest).map_err(|err| err.to_string())
+    }
+
+    pub fn remove(directory: &Path) -> Result<(), String> {
+        for path in [directory.join(LATEST), directory.join(PREVIOUS)] {
+            match fs::remove_file(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn load(directory: &Path) -> Option<Checkpoint> {
+        for path in [directory.join(LATEST), directory.join(PREVIOUS)] {
+            match Checkpoint::read(&path) {
+                Ok(Some(checkpoint)) => return Some(checkpoint),
+                Ok(None) => {}
+                Err(err) => eprintln!("Ignoring checkpoint {}: {err}", path.display()),
+            }
+        }
+
+        None
+    }
+
+    fn read(path: &PathBuf) -> Result<Option<Checkpoint>, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.to_string()),
+        };
+
+        Checkpoint::parse(&content).map(Some)
+    }
+
+    fn serialize(&self) -> String {
+        let entries = self.history.entries(now_secs());
+
+        let mut content = format!("offset;{}\n", self.journal_offset);
+        for entry in entries {
+            content.push_str(&format!("{entry}\n"));
+        }
+        let checksum = fnv1a_hash(content.as_bytes());
+        content.push_str(&format!("checksum;{checksum:016x}\n"));
+
+        content
+    }
+
+    fn parse(content: &str) -> Result<Checkpoint, String> {
+        let body_end = content
+            .trim_end_matches('\n')
+            .rfind('\n')
+            .map(|index| index + 1)
+            .ok_or("missing checksum")?;
+        let (body, checksum_line) = content.split_at(body_end);
+
+        let checksum = checksum_line
+            .trim_end()
+            .strip_prefix("checksum;")
+            .ok_or("missing checksum")?;
+        if checksum != format!("{:016x}", fnv1a_hash(body.as_bytes())) {
+            return Err("checksum mismatch".to_owned());
+        }
+
+        let mut lines = body.lines();
+        let journal_offset = lines
+            .next()
+            .and_then(|line| line.strip_prefix("offset;"))
+            .and_then(|offset| offset.parse().ok())
+            .ok_or("missing journal offset")?;
+
+        let mut history = JournalHistory::default();
+        for line in lines {
+            match JournalEntry::from_str(line)? {
+                JournalEntry::Pending { url, .. } => history.pending.push(url),
+                JournalEntry::Processing { url, .. } => history.processing.push(url),
+                JournalEntry::Processed { url, at, .. }
+                | JournalEntry::Probed { url, at, .. }
+                | JournalEntry::Duplicate { url, at } => history.processed.push((url, at)),
+                JournalEntry::Failed { url, .. } => history.failed.push(url),
+                JournalEntry::Retry { url, attempt, .. } => {
+                    history.attempts.insert(url, attempt);
+                }
+            }
+        }
+
+        Ok(Checkpoint {
+            history,
+            journal_offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::url::Url;
+
+    fn test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("yoink-checkpoint-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn checkpoint(offset: u64) -> Checkpoint {
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        Checkpoint {
+            history: JournalHistory {
+                pending: vec![url("a"), url("b")],
+                processing: vec![url("c")],
+                processed: vec![(url(""), 1_700_000_000)],
+                failed: vec![url("d")],
+                attempts: HashMap::from([(url("c"), 2)]),
+                ..JournalHistory::default()
+            },
+            journal_offset: offset,
+        }
+    }
+
+    #[test]
+    fn test_write_and_restore() {
+        let directory = test_directory("restore");
+        checkpoint(120).write(&directory).unwrap();
+
+        let restored = Checkpoint::load(&directory).unwrap();
+        let expected = checkpoint(120);
+        assert_eq!(restored.journal_offset, 120);
+        assert_eq!(restored.history.pending, expected.history.pending);
+        assert_eq!(restored.history.processing, expected.history.processing);
+        assert_eq!(restored.history.processed, expected.history.processed);
+        assert_eq!(restored.history.failed, expected.history.failed);
+        assert_eq!(restored.history.attempts, expected.history.attempts);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_checkpoint_falls_back_to_previous() {
+        let directory = test_directory("corrupt");
+        checkpoint(100).write(&directory).unwrap();
+        checkpoint(200).write(&directory).unwrap();
+        assert_eq!(Checkpoint::load(&directory).unwrap().journal_offset, 200);
+
+        let latest = directory.join(LATEST);
+        let content = fs::read_to_string(&latest).unwrap();
+        fs::write(&latest, content.replace("pending;", "failed;")).unwrap();
+        assert_eq!(Checkpoint::load(&directory).unwrap().journal_offset, 100);
+
+        fs::write(&latest, &content[..content.len() / 2]).unwrap();
+        assert_eq!(Checkpoint::load(&directory).unwrap().journal_offset, 100);
+
+        fs::remove_file(directory.join(PREVIOUS)).unwrap();
+        assert!(Checkpoint::load(&directory).is_none());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use clap::ValueEnum;
+use reqwest::{ClientBuilder, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HttpVersion {
+    #[value(name = "1.0")]
+    Http10,
+    #[value(name = "1.1")]
+    Http11,
+    #[value(name = "2")]
+    Http2,
+}
+
+impl HttpVersion {
+    pub fn configure(self, builder: ClientBuilder) -> ClientBuilder {
+        match self {
+            HttpVersion::Http10 | HttpVersion::Http11 => builder.http1_only(),
+            HttpVersion::Http2 => builder.http2_prior_knowledge(),
+        }
+    }
+
+    pub fn version(self) -> Version {
+        match self {
+            HttpVersion::Http10 => Version::HTTP_10,
+            HttpVersion::Http11 => Version::HTTP_11,
+            HttpVersion::Http2 => Version::HTTP_2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Client;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::*;
+    use crate::test_server::accept_request;
+
+    async fn request_line(http_version: HttpVersion) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut socket, request) = accept_request(&listener).await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            request.lines().next().unwrap_or_default().to_owned()
+        });
+
+        let client = http_version.configure(Client::builder()).build().unwrap();
+        let _ = client.get(url).version(http_version.version()).send().await;
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_client_uses_requested_version() {
+        assert_eq!(request_line(HttpVersion::Http10).await, "GET / HTTP/1.0");
+        assert_eq!(request_line(HttpVersion::Http11).await, "GET / HTTP/1.1");
+        assert_eq!(request_line(HttpVersion::Http2).await, "PRI * HTTP/2.0");
+    }
+}
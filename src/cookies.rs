@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use reqwest::{Url, cookie::Jar};
+
+use crate::json::JsonValue;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowserCookie {
+    pub name: String,
+    pub value: String,
+    pub domain: String,
+    pub path: String,
+    pub host_only: bool,
+    pub secure: bool,
+}
+
+impl BrowserCookie {
+    pub fn from_json(value: &JsonValue) -> Result<Self, String> {
+        let field = |name: &str| {
+            value
+                .get(name)
+                .and_then(JsonValue::as_str)
+                .ok_or(format!("missing string field {name:?}"))
+        };
+        let flag = |name: &str| matches!(value.get(name), Some(JsonValue::Bool(true)));
+
+        let name = field("name")?;
+        if name.is_empty() || name.contains(['=', ';']) {
+            return Err(format!("invalid cookie name {name:?}"));
+        }
+        let domain = field("domain")?;
+        if domain.trim_start_matches('.').is_empty() {
+            return Err("empty cookie domain".to_owned());
+        }
+
+        Ok(BrowserCookie {
+            name: name.to_owned(),
+            value: field("value")?.to_owned(),
+            domain: domain.to_owned(),
+            path: field("path").unwrap_or("/").to_owned(),
+            host_only: flag("hostOnly"),
+            secure: flag("secure"),
+        })
+    }
+
+    fn add_to(&self, jar: &Jar) -> Result<(), String> {
+        let host = self.domain.trim_start_matches('.');
+        let scheme = if self.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{scheme}://{host}{}", self.path))
+            .map_err(|err| err.to_string())?;
+
+        let mut cookie = format!("{}={}; Path={}", self.name, self.value, self.path);
+        if !self.host_only {
+            cookie.push_str(&format!("; Domain={host}"));
+        }
+        if self.secure {
+            cookie.push_str("; Secure");
+        }
+        jar.add_cookie_str(&cookie, &url);
+
+        Ok(())
+    }
+}
+
+pub fn parse_cookies_json(content: &str) -> Result<Vec<BrowserCookie>, String> {
+    let json = JsonValue::parse(content).map_err(|err| err.to_string())?;
+    let entries = json
+        .as_array()
+        .ok_or("cookie export must be a json array".to_owned())?;
+
+    Ok(entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match BrowserCookie::from_json(entry) {
+            Ok(cookie) => Some(cookie),
+            Err(err) => {
+                eprintln!("Skipping malformed cookie entry {i}: {err}");
+                None
+            }
+        })
+        .collect())
+}
+
+pub fn load_cookie_jar(path: &Path) -> Result<Jar, String> {
+    let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let jar = Jar::default();
+
+    for cookie in parse_cookies_json(&content)? {
+        if let Err(err) = cookie.add_to(&jar) {
+            eprintln!(
+                "Skipping cookie {} for {}: {err}",
+                cookie.name, cookie.domain
+            );
+        }
+    }
+
+    Ok(jar)
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::cookie::CookieStore;
+
+    use super::*;
+
+    const EXPORT: &str = r#"[
+        {"name": "session", "value": "abc123", "domain": ".example.com", "path": "/",
+         "secure": true, "httpOnly": true, "hostOnly": false, "expirationDate": 1900000000.5},
+        {"name": "theme", "value": "dark", "domain": "app.example.com", "path": "/dashboard",
+         "hostOnly": true},
+        {"name": "broken", "domain": "example.com"},
+        {"name": "", "value": "x", "domain": "example.com"},
+        "not a cookie"
+    ]"#;
+
+    #[test]
+    fn test_parse_cookie_export() {
+        let cookies = parse_cookies_json(EXPORT).unwrap();
+
+        assert_eq!(
+            cookies,
+            vec![
+                BrowserCookie {
+                    name: "session".to_owned(),
+                    value: "abc123".to_owned(),
+                    domain: ".example.com".to_owned(),
+                    path: "/".to_owned(),
+                    host_only: false,
+                    secure: true,
+                },
+                BrowserCookie {
+                    name: "theme".to_owned(),
+                    value: "dark".to_owned(),
+                    domain: "app.example.com".to_owned(),
+                    path: "/dashboard".to_owned(),
+                    host_only: true,
+                    secure: false,
+                },
+            ]
+        );
+
+        assert!(parse_cookies_json(r#"{"name": "session"}"#).is_err());
+        assert!(parse_cookies_json("[").is_err());
+    }
+
+    #[test]
+    fn test_cookies_added_to_jar() {
+        let jar = Jar::default();
+        for cookie in parse_cookies_json(EXPORT).unwrap() {
+            cookie.add_to(&jar).unwrap();
+        }
+
+        let cookies = |url: &str| {
+            jar.cookies(&url.parse().unwrap())
+                .map(|header| header.to_str().unwrap().to_owned())
+        };
+        assert_eq!(
+            cookies("https://www.example.com/"),
+            Some("session=abc123".to_owned())
+        );
+        assert_eq!(cookies("http://www.example.com/"), None);
+        assert_eq!(
+            cookies("http://app.example.com/dashboard/stats"),
+            Some("theme=dark".to_owned())
+        );
+        assert_eq!(cookies("http://other.example.com/dashboard"), None);
+    }
+}
@@ -0,0 +1,1705 @@
+use std::{str::FromStr, sync::Arc};
+
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+use reqwest::{
+    Client, StatusCode,
+    header::{AUTHORIZATION, CONTENT_TYPE, LOCATION, USER_AGENT},
+};
+use scraper::{Html, Selector};
+use tokio::{
+    sync::Mutex,
+    task::yield_now,
+    time::{Instant, Interval, timeout, timeout_at},
+};
+
+use crate::{
+    body_status::BodyStatusSelector,
+    client::HttpVersion,
+    dropped_links::DroppedLinks,
+    duplicates::{ContentDedupe, DuplicateContent, TitleDedupe},
+    filter::UrlFilter,
+    host_budget::HostBudget,
+    host_interval::HostInterval,
+    host_sample::HostSample,
+    inventory::Discoveries,
+    ip_limit::IpLimiter,
+    journal::{Journal, JournalEntry},
+    json::JsonValue,
+    link_selector::{LinkSelector, extract_links},
+    metrics::ByteCounter,
+    output::{OutputSink, Page},
+    queue::Queue,
+    redirect::{OffsiteRedirect, final_url, is_login_redirect, is_offsite, redirect_target},
+    refresh::now_secs,
+    retry::{FailureKind, RetryPolicy, UserAgentFallback, body_length_failure},
+    robots::Robots,
+    scope::{Scope, ScopeRule, in_scope},
+    timeouts::RequestTimeouts,
+    trace::{TraceEvent, Tracer},
+    url::{Credentials, FragmentPolicy, Url},
+};
+
+#[derive(Clone)]
+pub struct CrawlContext {
+    pub queue: Arc<Mutex<Queue>>,
+    pub journal: Journal,
+    pub client: Client,
+    pub http_version: Option<HttpVersion>,
+    pub credentials: Option<Credentials>,
+    pub base_url: Url,
+    pub scope: Scope,
+    pub extra_hosts: Arc<Vec<String>>,
+    pub scope_rules: Arc<Vec<ScopeRule>>,
+    pub link_selectors: Arc<Vec<LinkSelector>>,
+    pub json_link_paths: Arc<Vec<String>>,
+    pub sinks: Arc<Vec<OutputSink>>,
+    pub interval: Arc<Mutex<Interval>>,
+    pub priority_patterns: Arc<Vec<Regex>>,
+    pub login_url_pattern: Arc<Option<Regex>>,
+    pub robots: Arc<Option<Robots>>,
+    pub offsite_redirect: OffsiteRedirect,
+    pub retry_policy: RetryPolicy,
+    pub tracer: Tracer,
+    pub duplicates: Option<DuplicateContent>,
+    pub dropped_links: Option<DroppedLinks>,
+    pub content_dedupe: Option<ContentDedupe>,
+    pub title_dedupe: Option<TitleDedupe>,
+    pub downloaded: Option<ByteCounter>,
+    pub probe_only: bool,
+    pub allow_length_mismatch: bool,
+    pub fragments: FragmentPolicy,
+    pub ip_limiter: Option<Arc<IpLimiter>>,
+    pub discoveries: Option<Discoveries>,
+    pub body_status_selector: Arc<Option<BodyStatusSelector>>,
+    pub enqueue_batch_size: Option<usize>,
+    pub strip_session_params: bool,
+    pub host_budget: Option<HostBudget>,
+    pub print_extracted_links: bool,
+    pub max_dom_nodes: Option<usize>,
+    pub host_sample: Option<HostSample>,
+    pub url_filter: Arc<UrlFilter>,
+    pub user_agent_fallback: Option<UserAgentFallback>,
+    pub canonical_first: bool,
+    pub ignore_base_href: bool,
+    pub min_content_length: Option<usize>,
+    pub request_timeouts: Option<RequestTimeouts>,
+    pub host_interval: Option<HostInterval>,
+    pub content_types: Arc<Vec<String>>,
+    pub follow_srcset: bool,
+}
+
+impl CrawlContext {
+    async fn mark_as_processed(&mut self, url: &Url, status: Option<u16>) {
+        let at = now_secs();
+        let mut queue = self.queue.lock().await;
+        queue.mark_as_processed(url, at);
+        self.journal.send(JournalEntry::Processed {
+            url: url.to_owned(),
+            at,
+            status,
+        });
+    }
+
+    async fn mark_as_duplicate(&mut self, url: &Url) {
+        let at = now_secs();
+        let mut queue = self.queue.lock().await;
+        queue.mark_as_processed(url, at);
+        self.journal.send(JournalEntry::Duplicate {
+            url: url.to_owned(),
+            at,
+        });
+    }
+
+    async fn retry_or_fail(&mut self, url: &Url, kind: FailureKind, status: Option<u16>) {
+        let mut queue = self.queue.lock().await;
+        if self.retry_policy.should_retry(kind, queue.attempts(url)) {
+            let attempt = queue.retry(url);
+            self.journal.send(JournalEntry::Retry {
+                url: url.to_owned(),
+                attempt,
+                at: now_secs(),
+            });
+            self.tracer.trace(url, TraceEvent::Retried { attempt });
+            eprintln!("Retrying {url} (attempt {attempt})");
+            return;
+        }
+
+        queue.mark_as_failed(url);
+        self.journal.send(JournalEntry::Failed {
+            url: url.to_owned(),
+            at: now_secs(),
+            status,
+        });
+    }
+
+    async fn enqueue_links(&mut self, from: &Url, links: &[Url]) {
+        let batch_size = self.enqueue_batch_size.unwrap_or(links.len()).max(1);
+
+        for batch in links.chunks(batch_size) {
+            {
+                let mut queue = self.queue.lock().await;
+                let depth = queue.depth(from) + 1;
+                for link in batch {
+                    if queue.exceeds_max_depth(depth) {
+                        let from = from.clone();
+                        self.tracer.trace(link, TraceEvent::TooDeep { from, depth });
+                        continue;
+                    }
+                    if !queue.admits_host(&link.host) {
+                        let from = from.clone();
+                        self.tracer
+                            .trace(link, TraceEvent::HostLimitReached { from });
+                        continue;
+                    }
+
+                    let added = if is_priority(&self.priority_patterns, link) {
+                        queue.add_pending_priority(link, depth)
+                    } else {
+                        queue.add_pending_with_depth(link, depth)
+                    };
+
+                    if added && let Some(discoveries) = &self.discoveries {
+                        discoveries.record(link, Some(from), now_secs());
+                    }
+
+                    let from = from.clone();
+                    if added {
+                        self.tracer.trace(link, TraceEvent::Enqueued { from });
+                    } else {
+                        self.tracer.trace(link, TraceEvent::Deduplicated { from });
+                    }
+
+                    self.journal.send(JournalEntry::Pending {
+                        url: link.to_owned(),
+                        at: now_secs(),
+                    });
+                }
+            }
+
+            if batch.len() < links.len() {
+                yield_now().await;
+            }
+        }
+    }
+
+    async fn mark_as_failed(&mut self, url: &Url, status: Option<u16>) {
+        let mut queue = self.queue.lock().await;
+        queue.mark_as_failed(url);
+        self.journal.send(JournalEntry::Failed {
+            url: url.to_owned(),
+            at: now_secs(),
+            status,
+        });
+    }
+}
+
+pub async fn crawl_url(mut ctx: CrawlContext, url: Url) {
+    if let Some(host_budget) = &ctx.host_budget
+        && host_budget.is_exhausted(&url.host)
+    {
+        ctx.tracer.trace(&url, TraceEvent::HostBudgetExhausted);
+        eprintln!("Skipping {url}, time budget for {} is used up", url.host);
+        ctx.mark_as_failed(&url, None).await;
+        return;
+    }
+    if let Some(host_sample) = &ctx.host_sample
+        && !host_sample.claim(&url.host)
+    {
+        ctx.tracer.trace(&url, TraceEvent::HostSampleFull);
+        eprintln!(
+            "Skipping {url}, already sampled enough pages from {}",
+            url.host
+        );
+        ctx.mark_as_failed(&url, None).await;
+        return;
+    }
+
+    {
+        let mut interval = ctx.interval.lock().await;
+        interval.tick().await;
+    }
+    if let Some(host_interval) = &ctx.host_interval {
+        host_interval.tick(&url.host).await;
+    }
+
+    let _ip_permit = match &ctx.ip_limiter {
+        Some(ip_limiter) => ip_limiter.acquire(&url.host).await,
+        None => None,
+    };
+    let _host_timer = ctx
+        .host_budget
+        .as_ref()
+        .map(|host_budget| host_budget.start(&url.host));
+
+    let started = Instant::now();
+    let send = async |user_agent: Option<&str>| {
+        let mut request = ctx.client.get(url.request_url());
+        if let Some(http_version) = ctx.http_version {
+            request = request.version(http_version.version());
+        }
+        if let Some(credentials) = &ctx.credentials
+            && url.scheme == ctx.base_url.scheme
+            && url.same_authority(&ctx.base_url)
+        {
+            request = request.header(AUTHORIZATION, credentials.authorization());
+        }
+        if let Some(user_agent) = user_agent {
+            request = request.header(USER_AGENT, user_agent);
+        }
+        match ctx.request_timeouts {
+            Some(timeouts) => {
+                let sent = request.timeout(timeouts.longest()).send();
+                match timeout(timeouts.for_url(&url), sent).await {
+                    Ok(resp) => resp.map_err(|err| err.to_string()),
+                    Err(_) => Err("timed out waiting for response headers".to_owned()),
+                }
+            }
+            None => request.send().await.map_err(|err| err.to_string()),
+        }
+    };
+
+    let mut resp = send(None).await;
+    if let Ok(forbidden) = &resp
+        && let Some(fallback) = &ctx.user_agent_fallback
+        && let Some(user_agent) = fallback.for_status(forbidden.status())
+    {
+        ctx.tracer.trace(
+            &url,
+            TraceEvent::Fetched {
+                status: forbidden.status(),
+            },
+        );
+        ctx.tracer.trace(
+            &url,
+            TraceEvent::UserAgentSwitched {
+                user_agent: user_agent.to_owned(),
+            },
+        );
+        resp = send(Some(user_agent)).await;
+        if let Ok(retried) = &resp
+            && retried.status().is_success()
+        {
+            eprintln!("Fetched {url} with fallback user agent {user_agent}");
+            fallback.record_success(user_agent);
+        }
+    }
+
+    let resp = match resp {
+        Ok(r) => r,
+        Err(err) => {
+            ctx.tracer.trace(&url, TraceEvent::RequestFailed);
+            eprintln!("Request failed for {url}: {err}");
+            ctx.retry_or_fail(&url, FailureKind::Request, None).await;
+            return;
+        }
+    };
+
+    ctx.tracer.trace(
+        &url,
+        TraceEvent::Fetched {
+            status: resp.status(),
+        },
+    );
+
+    let location = resp
+        .headers()
+        .get(LOCATION)
+        .and_then(|location| location.to_str().ok());
+
+    if let Some(login_url_pattern) = ctx.login_url_pattern.as_ref()
+        && is_login_redirect(resp.status(), resp.url(), location, login_url_pattern)
+    {
+        let mut queue = ctx.queue.lock().await;
+        queue.mark_as_auth_required(&url);
+        ctx.journal.send(JournalEntry::Failed {
+            url: url.to_owned(),
+            at: now_secs(),
+            status: Some(resp.status().as_u16()),
+        });
+        ctx.tracer.trace(&url, TraceEvent::AuthRequired);
+        eprintln!("Authentication required for {url}");
+        return;
+    }
+
+    if ctx.offsite_redirect != OffsiteRedirect::Follow
+        && let Some(target) = redirect_target(resp.status(), resp.url(), location)
+        && is_offsite(
+            &ctx.base_url,
+            ctx.scope,
+            &ctx.extra_hosts,
+            &ctx.scope_rules,
+            &target,
+        )
+    {
+        ctx.tracer.trace(&url, TraceEvent::OffsiteRedirect);
+        if ctx.offsite_redirect == OffsiteRedirect::Record {
+            println!("Recorded off-site redirect {url} -> {target}");
+        }
+        ctx.mark_as_processed(&url, Some(resp.status().as_u16()))
+            .await;
+        return;
+    }
+
+    if ctx.probe_only {
+        let status = resp.status();
+        println!("{} {url}", status.as_u16());
+        let at = now_secs();
+        let mut queue = ctx.queue.lock().await;
+        queue.mark_as_processed(&url, at);
+        ctx.journal.send(JournalEntry::Probed {
+            url: url.to_owned(),
+            status: status.as_u16(),
+            at,
+        });
+        return;
+    }
+
+    let requested = url;
+    let url = final_url(&resp).unwrap_or_else(|_| requested.clone());
+    if url != requested {
+        ctx.tracer
+            .trace(&requested, TraceEvent::Redirected { to: url.clone() });
+        if ctx.offsite_redirect != OffsiteRedirect::Follow
+            && !in_scope(
+                &ctx.base_url,
+                &url,
+                ctx.scope,
+                &ctx.extra_hosts,
+                &ctx.scope_rules,
+            )
+        {
+            ctx.tracer.trace(&requested, TraceEvent::OffsiteRedirect);
+            ctx.mark_as_processed(&requested, None).await;
+            return;
+        }
+
+        let claimed = {
+            let mut queue = ctx.queue.lock().await;
+            let depth = queue.depth(&requested);
+            queue.claim(&url, depth)
+        };
+        ctx.mark_as_processed(&requested, None).await;
+        if !claimed {
+            return;
+        }
+    }
+
+    let status = resp.status();
+    if status.is_client_error() || status.is_server_error() {
+        eprintln!("Not saving {url}, server responded with {status}");
+        ctx.mark_as_failed(&url, Some(status.as_u16())).await;
+        return;
+    }
+    if status.is_redirection() {
+        let target = redirect_target(status, resp.url(), location)
+            .and_then(|target| Url::from_str(target.as_str()).ok())
+            .map(|target| target.normalize());
+        if let Some(target) = target {
+            ctx.tracer
+                .trace(&url, TraceEvent::Redirected { to: target.clone() });
+            if in_scope(
+                &ctx.base_url,
+                &target,
+                ctx.scope,
+                &ctx.extra_hosts,
+                &ctx.scope_rules,
+            ) && ctx.url_filter.allows(&target)
+            {
+                ctx.enqueue_links(&url, &[target]).await;
+            }
+        }
+        ctx.mark_as_processed(&url, Some(status.as_u16())).await;
+        return;
+    }
+    if status == StatusCode::NO_CONTENT {
+        ctx.mark_as_processed(&url, Some(status.as_u16())).await;
+        return;
+    }
+
+    let content_type = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_owned());
+    let is_json = content_type.as_deref().is_some_and(is_json_content_type);
+    let content_length = resp.content_length();
+
+    if (!is_json || ctx.json_link_paths.is_empty())
+        && let Some(content_type) = &content_type
+        && !is_allowed_content_type(&ctx.content_types, content_type)
+    {
+        ctx.tracer.trace(
+            &url,
+            TraceEvent::UnwantedContentType {
+                content_type: content_type.to_owned(),
+            },
+        );
+        ctx.mark_as_processed(&url, Some(status.as_u16())).await;
+        return;
+    }
+
+    let body = resp.bytes();
+    let body = match ctx.request_timeouts {
+        Some(timeouts) => {
+            match timeout_at(started + timeouts.for_body(&url, content_length), body).await {
+                Ok(body) => body.map_err(|err| err.to_string()),
+                Err(_) => Err("timed out".to_owned()),
+            }
+        }
+        None => body.await.map_err(|err| err.to_string()),
+    };
+    let bytes = match body {
+        Ok(b) => b,
+        Err(err) => {
+            ctx.tracer.trace(&url, TraceEvent::BodyReadFailed);
+            eprintln!("Failed to read body for {url}: {err}");
+            ctx.retry_or_fail(&url, FailureKind::BodyRead, Some(status.as_u16()))
+                .await;
+            return;
+        }
+    };
+
+    if !ctx.allow_length_mismatch
+        && let Some(kind) = body_length_failure(content_length, bytes.len())
+    {
+        ctx.tracer.trace(&url, TraceEvent::BodyReadFailed);
+        eprintln!(
+            "Body for {url} is {} bytes but Content-Length is {}",
+            bytes.len(),
+            content_length.unwrap_or_default()
+        );
+        ctx.retry_or_fail(&url, kind, Some(status.as_u16())).await;
+        return;
+    }
+    if let Some(downloaded) = &ctx.downloaded {
+        downloaded.add(bytes.len());
+    }
+    if let Some(content_dedupe) = &ctx.content_dedupe
+        && let Some(original) = content_dedupe.original(&url, &bytes)
+    {
+        eprintln!("Skipping {url}, same content as {original}");
+        ctx.tracer
+            .trace(&url, TraceEvent::DuplicateContent { of: original });
+        ctx.mark_as_duplicate(&url).await;
+        return;
+    }
+    let body = decode_body(&bytes, content_type.as_deref());
+
+    let (mut hrefs, mut canonical, mut link_base, mut title) = (vec![], None, None, None);
+    let body_status = if is_json && !ctx.json_link_paths.is_empty() {
+        hrefs = extract_links_from_json(&body, &ctx.json_link_paths);
+        None
+    } else {
+        match parse_document(&body, ctx.max_dom_nodes) {
+            Err(nodes) => {
+                ctx.tracer.trace(&url, TraceEvent::DomTooLarge { nodes });
+                eprintln!("Skipping link extraction for {url}, document has {nodes} nodes");
+                None
+            }
+            Ok(document) => {
+                hrefs = extract_links(&document, &ctx.link_selectors);
+                if ctx.follow_srcset {
+                    hrefs.extend(extract_srcset_urls(&document));
+                }
+                if !ctx.ignore_base_href {
+                    link_base = extract_base_href(&document)
+                        .and_then(|href| Url::resolve_base(&url, &href, ctx.fragments).ok());
+                }
+                if ctx.canonical_first {
+                    canonical = extract_canonical(&document);
+                }
+                if ctx.title_dedupe.is_some() {
+                    title = extract_title(&document);
+                }
+                (*ctx.body_status_selector)
+                    .as_ref()
+                    .and_then(|selector| selector.status(&document))
+            }
+        }
+    };
+    hrefs.extend(canonical.clone());
+    if ctx.print_extracted_links {
+        eprint!("{}", format_extracted_links(&url, &hrefs));
+    }
+    if let Some(status) = body_status {
+        ctx.tracer
+            .trace(&url, TraceEvent::StatusFromBody { status });
+        if (400..600).contains(&status) {
+            eprintln!("Not saving {url}, page reports status {status}");
+            ctx.mark_as_failed(&url, Some(status)).await;
+            return;
+        }
+    }
+    let recorded_status = body_status.unwrap_or(status.as_u16());
+
+    let link_base = link_base.as_ref().unwrap_or(&url);
+    let links: Vec<Url> = hrefs
+        .iter()
+        .filter_map(
+            |url_or_path| match Url::resolve(link_base, url_or_path, ctx.fragments) {
+                Ok(link) => Some(link),
+                Err(err) => {
+                    if let Some(dropped_links) = &ctx.dropped_links {
+                        dropped_links.record(&url, url_or_path, &err);
+                    }
+                    None
+                }
+            },
+        )
+        .map(|link| link.normalize())
+        .map(|link| {
+            if ctx.strip_session_params {
+                link.without_session_params()
+            } else {
+                link
+            }
+        })
+        .filter(|link| {
+            let exhausted = ctx
+                .host_budget
+                .as_ref()
+                .is_some_and(|host_budget| host_budget.is_exhausted(&link.host));
+            if exhausted {
+                ctx.tracer.trace(link, TraceEvent::HostBudgetExhausted);
+            }
+            !exhausted
+        })
+        .filter(|link| {
+            let full = ctx
+                .host_sample
+                .as_ref()
+                .is_some_and(|host_sample| host_sample.is_full(&link.host));
+            if full {
+                ctx.tracer.trace(link, TraceEvent::HostSampleFull);
+            }
+            !full
+        })
+        .filter(|link| {
+            let in_scope = in_scope(
+                &ctx.base_url,
+                link,
+                ctx.scope,
+                &ctx.extra_hosts,
+                &ctx.scope_rules,
+            );
+            if !in_scope {
+                ctx.tracer
+                    .trace(link, TraceEvent::OutOfScope { from: url.clone() });
+            }
+            in_scope
+        })
+        .filter(|link| {
+            let allowed = ctx.url_filter.allows(link);
+            if !allowed {
+                ctx.tracer
+                    .trace(link, TraceEvent::Excluded { from: url.clone() });
+            }
+            allowed
+        })
+        .filter(|link| {
+            let allowed = robots_allow(&ctx.robots, &ctx.base_url, link);
+            if !allowed {
+                ctx.tracer
+                    .trace(link, TraceEvent::DisallowedByRobots { from: url.clone() });
+            }
+            allowed
+        })
+        .collect();
+
+    ctx.enqueue_links(&url, &links).await;
+    if let Some(canonical) = canonical
+        .and_then(|href| Url::resolve(link_base, &href, ctx.fragments).ok())
+        .map(|canonical| canonical.normalize())
+        && links.contains(&canonical)
+    {
+        ctx.queue.lock().await.promote(&canonical);
+    }
+
+    if ctx
+        .min_content_length
+        .is_some_and(|min_content_length| bytes.len() < min_content_length)
+    {
+        ctx.tracer
+            .trace(&url, TraceEvent::TooSmall { bytes: bytes.len() });
+        eprintln!("Not saving {url}, body is only {} bytes", bytes.len());
+        ctx.mark_as_processed(&url, Some(recorded_status)).await;
+        return;
+    }
+
+    if let Some(title_dedupe) = &ctx.title_dedupe
+        && let Some(original) = title
+            .as_deref()
+            .and_then(|title| title_dedupe.original(&url, title))
+    {
+        eprintln!("Not saving {url}, same title as {original}");
+        ctx.tracer
+            .trace(&url, TraceEvent::DuplicateTitle { of: original });
+        ctx.mark_as_duplicate(&url).await;
+        return;
+    }
+
+    let page = Page {
+        url: &url,
+        body: &body,
+        links: &links,
+        status: status.as_u16(),
+        content_type: content_type.as_deref(),
+        raw: &bytes,
+        discovery: ctx
+            .discoveries
+            .as_ref()
+            .and_then(|discoveries| discoveries.get(&url)),
+    };
+    for sink in ctx.sinks.iter() {
+        if let Err(err) = sink.write(&page).await {
+            ctx.tracer.trace(&url, TraceEvent::SaveFailed);
+            println!("Failed to save output for {url}: {err}");
+            if err.is_storage_full() {
+                ctx.retry_or_fail(&url, FailureKind::Save, Some(recorded_status))
+                    .await;
+            } else {
+                ctx.mark_as_failed(&url, Some(recorded_status)).await;
+            }
+            return;
+        }
+    }
+
+    if let Some(duplicates) = &ctx.duplicates {
+        duplicates.record(&url, &body);
+    }
+
+    ctx.tracer.trace(&url, TraceEvent::Saved);
+    ctx.mark_as_processed(&url, Some(recorded_status)).await;
+}
+
+fn robots_allow(robots: &Option<Robots>, base_url: &Url, url: &Url) -> bool {
+    match robots {
+        Some(robots) if url.same_authority(base_url) => robots.is_allowed(url),
+        _ => true,
+    }
+}
+
+fn parse_document(body: &str, max_dom_nodes: Option<usize>) -> Result<Html, usize> {
+    let Some(max_dom_nodes) = max_dom_nodes else {
+        return Ok(Html::parse_document(body));
+    };
+
+    // Every start tag opens at least one element, so oversized input is rejected before parsing
+    let start_tags = body
+        .as_bytes()
+        .windows(2)
+        .filter(|pair| pair[0] == b'<' && pair[1].is_ascii_alphabetic())
+        .count();
+    if start_tags > max_dom_nodes {
+        return Err(start_tags);
+    }
+
+    let document = Html::parse_document(body);
+    let nodes = document.tree.root().descendants().count();
+    if nodes > max_dom_nodes {
+        Err(nodes)
+    } else {
+        Ok(document)
+    }
+}
+
+fn extract_srcset_urls(document: &Html) -> Vec<String> {
+    let selector = Selector::parse("img[srcset], source[srcset]").expect("Invalid selector");
+    document
+        .select(&selector)
+        .filter_map(|element| element.attr("srcset"))
+        .flat_map(parse_srcset)
+        .collect()
+}
+
+fn parse_srcset(srcset: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = srcset;
+
+    loop {
+        rest = rest.trim_start_matches(|ch: char| ch.is_ascii_whitespace() || ch == ',');
+        if rest.is_empty() {
+            return urls;
+        }
+
+        let url_end = rest
+            .find(|ch: char| ch.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        let url = &rest[..url_end];
+        rest = &rest[url_end..];
+        if let Some(url) = url.strip_suffix(',') {
+            urls.push(url.trim_end_matches(',').to_owned());
+            continue;
+        }
+        urls.push(url.to_owned());
+
+        let mut depth = 0usize;
+        let descriptors_end = rest
+            .char_indices()
+            .find(|(_, ch)| {
+                match ch {
+                    '(' => depth += 1,
+                    ')' => depth = depth.saturating_sub(1),
+                    ',' if depth == 0 => return true,
+                    _ => {}
+                }
+                false
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        rest = &rest[descriptors_end..];
+    }
+}
+
+fn extract_base_href(document: &Html) -> Option<String> {
+    let selector = Selector::parse("base[href]").expect("Invalid selector");
+    document
+        .select(&selector)
+        .find_map(|base| base.attr("href").map(String::from))
+}
+
+fn extract_canonical(document: &Html) -> Option<String> {
+    let selector = Selector::parse(r#"link[rel~="canonical"][href]"#).expect("Invalid selector");
+    document
+        .select(&selector)
+        .find_map(|link| link.attr("href").map(String::from))
+}
+
+fn extract_title(document: &Html) -> Option<String> {
+    let selector = Selector::parse("title").expect("Invalid selector");
+    document
+        .select(&selector)
+        .next()
+        .map(|title| title.text().collect())
+}
+
+fn format_extracted_links(url: &Url, hrefs: &[String]) -> String {
+    let mut report = format!("Extracted {} links from {url}:\n", hrefs.len());
+    for href in hrefs {
+        report.push_str(&format!("  {href}\n"));
+    }
+    report
+}
+
+fn extract_links_from_json(body: &str, json_link_paths: &[String]) -> Vec<String> {
+    let value = match JsonValue::parse(body) {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("Failed to parse json body: {err}");
+            return Vec::new();
+        }
+    };
+
+    json_link_paths
+        .iter()
+        .flat_map(|path| value.select(path))
+        .filter_map(|link| link.as_str().map(String::from))
+        .collect()
+}
+
+fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| value.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(UTF_8);
+
+    let (body, _, _) = encoding.decode(bytes);
+    body.into_owned()
+}
+
+fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn is_json_content_type(content_type: &str) -> bool {
+    let essence = media_type(content_type);
+
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+fn is_allowed_content_type(content_types: &[String], content_type: &str) -> bool {
+    let essence = media_type(content_type);
+
+    content_types.is_empty()
+        || content_types.iter().any(|allowed| {
+            let allowed = allowed.trim().to_ascii_lowercase();
+            match allowed.strip_suffix("/*") {
+                Some(kind) => essence.split('/').next() == Some(kind),
+                None => essence == allowed,
+            }
+        })
+}
+
+fn is_priority(priority_patterns: &[Regex], url: &Url) -> bool {
+    let url = url.to_string();
+    priority_patterns
+        .iter()
+        .any(|pattern| pattern.is_match(&url))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::AsyncWriteExt,
+        net::TcpListener,
+        sync::oneshot,
+        time::{interval, sleep, timeout},
+    };
+
+    use super::*;
+    use crate::{
+        filter::UrlPattern,
+        output::{OutputFormat, SaveOptions},
+        test_server::{accept_request, response, serve},
+        url::{Credentials, FilenameScheme, UrlError},
+    };
+
+    fn test_context(name: &str, base_url: &Url) -> CrawlContext {
+        let journal_path =
+            std::env::temp_dir().join(format!("yoink-{name}-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&journal_path);
+        let (journal, journal_task) = Journal::new(journal_path, None).unwrap();
+        tokio::spawn(journal_task);
+
+        CrawlContext {
+            queue: Arc::new(Mutex::new(Queue::new_with_initial(
+                base_url,
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+            ))),
+            journal,
+            client: Client::new(),
+            http_version: None,
+            credentials: None,
+            base_url: base_url.to_owned(),
+            scope: Scope::Host,
+            extra_hosts: Arc::new(vec![]),
+            scope_rules: Arc::new(vec![]),
+            link_selectors: Arc::new(vec![LinkSelector::from_str("a").unwrap()]),
+            json_link_paths: Arc::new(vec![]),
+            sinks: Arc::new(vec![]),
+            interval: Arc::new(Mutex::new(interval(Duration::from_millis(1)))),
+            priority_patterns: Arc::new(vec![]),
+            login_url_pattern: Arc::new(None),
+            robots: Arc::new(None),
+            offsite_redirect: OffsiteRedirect::Skip,
+            retry_policy: RetryPolicy::default(),
+            tracer: Tracer::default(),
+            duplicates: None,
+            dropped_links: None,
+            content_dedupe: None,
+            title_dedupe: None,
+            downloaded: None,
+            probe_only: false,
+            allow_length_mismatch: false,
+            fragments: FragmentPolicy::Strip,
+            ip_limiter: None,
+            discoveries: None,
+            body_status_selector: Arc::new(None),
+            enqueue_batch_size: None,
+            strip_session_params: false,
+            host_budget: None,
+            print_extracted_links: false,
+            max_dom_nodes: None,
+            host_sample: None,
+            url_filter: Arc::new(UrlFilter::default()),
+            user_agent_fallback: None,
+            canonical_first: false,
+            ignore_base_href: false,
+            min_content_length: None,
+            request_timeouts: None,
+            host_interval: None,
+            content_types: Arc::new(vec![]),
+            follow_srcset: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_not_locked_during_body_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url =
+            Url::from_str(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let (headers_sent_tx, headers_sent_rx) = oneshot::channel();
+        let (finish_tx, finish_rx) = oneshot::channel::<()>();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = accept_request(&listener).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\n<html>")
+                .await
+                .unwrap();
+            headers_sent_tx.send(()).unwrap();
+            finish_rx.await.unwrap();
+            socket.write_all(b"</html>").await.unwrap();
+        });
+
+        let ctx = test_context("body-read-lock", &base_url);
+        let queue = ctx.queue.clone();
+        let task = tokio::spawn(crawl_url(ctx, base_url));
+
+        headers_sent_rx.await.unwrap();
+        sleep(Duration::from_millis(50)).await;
+        assert!(queue.try_lock().is_ok());
+
+        finish_tx.send(()).unwrap();
+        task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trace_url_decisions() {
+        let address = serve(vec![
+            (
+                "/",
+                response(
+                    "200 OK",
+                    &[],
+                    r#"<a href="/target">a</a><a href="/target">b</a><a href="https://elsewhere.com/target">c</a>"#,
+                ),
+            ),
+            ("/target", response("200 OK", &[], "<p>target</p>")),
+        ])
+        .await;
+
+        let base_url = Url::from_str(&address).unwrap();
+        let target = Url::from_str(&format!("{address}/target")).unwrap();
+        let offsite = Url::from_str("https://elsewhere.com/target").unwrap();
+
+        let mut ctx = test_context("trace-url", &base_url);
+        ctx.tracer = Tracer::new(Some(target.clone()));
+        crawl_url(ctx.clone(), base_url.clone()).await;
+        crawl_url(ctx.clone(), target.clone()).await;
+
+        assert_eq!(
+            ctx.tracer.events(),
+            vec![
+                TraceEvent::Enqueued {
+                    from: base_url.clone()
+                },
+                TraceEvent::Deduplicated {
+                    from: base_url.clone()
+                },
+                TraceEvent::Fetched {
+                    status: StatusCode::OK
+                },
+                TraceEvent::Saved,
+            ]
+        );
+
+        let mut ctx = test_context("trace-url-offsite", &base_url);
+        ctx.tracer = Tracer::new(Some(offsite));
+        crawl_url(ctx.clone(), base_url.clone()).await;
+        assert_eq!(
+            ctx.tracer.events(),
+            vec![TraceEvent::OutOfScope { from: base_url }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_status_handling() {
+        let address = serve(vec![
+            ("/", response("200 OK", &[], r#"<a href="/gone">gone</a>"#)),
+            (
+                "/gone",
+                response("404 Not Found", &[], r#"<a href="/from-404">x</a>"#),
+            ),
+            ("/error", response("500 Internal Server Error", &[], "oops")),
+            ("/empty", response("204 No Content", &[], "")),
+            (
+                "/again",
+                response("200 OK", &[], r#"<a href="/gone">gone</a>"#),
+            ),
+            (
+                "/moved",
+                response("301 Moved Permanently", &[("Location", "/new")], ""),
+            ),
+        ])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let url = |path: &str| Url::from_str(&format!("{address}{path}")).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("yoink-status-handling-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut ctx = test_context("status-handling", &base_url);
+        ctx.client = Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        ctx.sinks = Arc::new(vec![
+            OutputSink::new(OutputFormat::Html, &directory, &SaveOptions::default()).unwrap(),
+        ]);
+
+        for path in ["/gone", "", "/error", "/empty", "/moved", "/again"] {
+            crawl_url(ctx.clone(), url(path)).await;
+        }
+
+        let history = ctx.queue.lock().await.snapshot();
+        let mut failed: Vec<String> = history.failed.iter().map(Url::to_string).collect();
+        failed.sort();
+        assert_eq!(
+            failed,
+            vec![url("/error").to_string(), url("/gone").to_string()]
+        );
+        assert_eq!(history.processed.len(), 4);
+        let pending: Vec<&Url> = history.pending.iter().collect();
+        assert!(pending.contains(&&url("/new")));
+        assert!(!pending.contains(&&url("/from-404")));
+        assert!(!pending.contains(&&url("/gone")));
+
+        let html = directory.join("html");
+        assert!(
+            base_url
+                .storage_path(&html, FilenameScheme::Encoded)
+                .exists()
+        );
+        for path in ["/gone", "/error", "/empty", "/moved"] {
+            assert!(
+                !url(path)
+                    .storage_path(&html, FilenameScheme::Encoded)
+                    .exists()
+            );
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_from_body() {
+        let page = |status: &str| {
+            format!(
+                r#"<meta http-equiv="Status" content="{status}"><a href="/hidden-{status}">x</a>"#
+            )
+        };
+        let address = serve(vec![
+            ("/soft-404", response("200 OK", &[], &page("404"))),
+            ("/soft-203", response("200 OK", &[], &page("203"))),
+        ])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let url = |path: &str| Url::from_str(&format!("{address}{path}")).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("yoink-body-status-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut ctx = test_context("body-status", &base_url);
+        ctx.body_status_selector = Arc::new(Some(
+            BodyStatusSelector::from_str(r#"meta[http-equiv="Status"]@content"#).unwrap(),
+        ));
+        ctx.sinks = Arc::new(vec![
+            OutputSink::new(OutputFormat::Warc, &directory, &SaveOptions::default()).unwrap(),
+        ]);
+
+        for path in ["/soft-404", "/soft-203"] {
+            crawl_url(ctx.clone(), url(path)).await;
+        }
+
+        let history = ctx.queue.lock().await.snapshot();
+        assert_eq!(history.failed, vec![url("/soft-404")]);
+        assert!(!history.pending.contains(&url("/hidden-404")));
+        assert!(history.pending.contains(&url("/hidden-203")));
+
+        ctx.journal.flush_now().await;
+        let journal_path =
+            std::env::temp_dir().join(format!("yoink-body-status-{}.log", std::process::id()));
+        let journal = std::fs::read_to_string(&journal_path).unwrap();
+        assert!(journal.contains(&format!(";404;{}\n", url("/soft-404"))));
+        assert!(journal.contains(&format!(";203;{}\n", url("/soft-203"))));
+
+        let warc = std::fs::read_to_string(directory.join("crawl.warc")).unwrap();
+        assert_eq!(warc.matches("HTTP/1.1 200 OK\r\n").count(), 1);
+        assert!(!warc.contains("HTTP/1.1 203"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_followed_redirect_saved_under_final_url() {
+        let address = serve(vec![
+            (
+                "/old",
+                response("301 Moved Permanently", &[("Location", "/new")], ""),
+            ),
+            ("/alias", response("302 Found", &[("Location", "/new")], "")),
+            (
+                "/new",
+                response("200 OK", &[], r#"<a href="next">next</a>"#),
+            ),
+        ])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let url = |path: &str| Url::from_str(&format!("{address}{path}")).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("yoink-final-url-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut ctx = test_context("final-url", &base_url);
+        ctx.sinks = Arc::new(vec![
+            OutputSink::new(OutputFormat::Html, &directory, &SaveOptions::default()).unwrap(),
+        ]);
+
+        crawl_url(ctx.clone(), url("/old")).await;
+        crawl_url(ctx.clone(), url("/alias")).await;
+
+        let html = directory.join("html");
+        assert!(
+            url("/new")
+                .storage_path(&html, FilenameScheme::Encoded)
+                .exists()
+        );
+        assert!(
+            !url("/old")
+                .storage_path(&html, FilenameScheme::Encoded)
+                .exists()
+        );
+        let history = ctx.queue.lock().await.snapshot();
+        let mut processed: Vec<String> = history
+            .processed
+            .iter()
+            .map(|(url, _)| url.to_string())
+            .collect();
+        processed.sort();
+        assert_eq!(
+            processed,
+            vec![
+                url("/alias").to_string(),
+                url("/new").to_string(),
+                url("/old").to_string()
+            ]
+        );
+        assert!(history.pending.contains(&url("/next")));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_probe_only_skips_body_reads() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let mut open_sockets = Vec::new();
+            loop {
+                let (mut socket, request) = accept_request(&listener).await;
+                let status = if request.starts_with("GET /missing ") {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                let headers = format!("HTTP/1.1 {status}\r\nContent-Length: 1000\r\n\r\n<html>");
+                socket.write_all(headers.as_bytes()).await.unwrap();
+                open_sockets.push(socket);
+            }
+        });
+
+        let base_url = Url::from_str(&address).unwrap();
+        let missing = Url::from_str(&format!("{address}/missing")).unwrap();
+        let mut ctx = test_context("probe-only", &base_url);
+        ctx.probe_only = true;
+        ctx.queue.lock().await.add_pending(&missing);
+
+        for url in [&base_url, &missing] {
+            timeout(Duration::from_secs(5), crawl_url(ctx.clone(), url.clone()))
+                .await
+                .expect("probe waited for the response body");
+        }
+
+        ctx.journal.flush_now().await;
+        let journal_path =
+            std::env::temp_dir().join(format!("yoink-probe-only-{}.log", std::process::id()));
+        let statuses: Vec<(u16, String)> = std::fs::read_to_string(&journal_path)
+            .unwrap()
+            .lines()
+            .map(|line| match JournalEntry::from_str(line).unwrap() {
+                JournalEntry::Probed { url, status, .. } => (status, url.to_string()),
+                _ => panic!("unexpected journal entry {line}"),
+            })
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![(200, base_url.to_string()), (404, missing.to_string())]
+        );
+        assert_eq!(
+            Journal::load_history(journal_path).unwrap().processed.len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_oversized_document_skips_extraction() {
+        let body = r#"<ul><li><a href="/a">a</a></li><li><a href="/b">b</a></li></ul>"#;
+        let address = serve(vec![("/", response("200 OK", &[], body))]).await;
+        let base_url = Url::from_str(&address).unwrap();
+
+        for (max_dom_nodes, pending) in [(Some(5), 0), (Some(1000), 2), (None, 2)] {
+            let directory = std::env::temp_dir().join(format!(
+                "yoink-max-dom-nodes-{max_dom_nodes:?}-{}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&directory);
+            let mut ctx = test_context("max-dom-nodes", &base_url);
+            ctx.max_dom_nodes = max_dom_nodes;
+            ctx.sinks = Arc::new(vec![
+                OutputSink::new(OutputFormat::Html, &directory, &SaveOptions::default()).unwrap(),
+            ]);
+            ctx.queue.lock().await.next();
+
+            crawl_url(ctx.clone(), base_url.clone()).await;
+
+            let saved = base_url.storage_path(&directory.join("html"), FilenameScheme::Encoded);
+            assert_eq!(std::fs::read_to_string(saved).unwrap(), body);
+            let mut queue = ctx.queue.lock().await;
+            assert_eq!(
+                std::iter::from_fn(|| queue.next().map(|(url, _)| url)).count(),
+                pending
+            );
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_document_node_limit() {
+        let nested = "<b>".repeat(1000);
+        assert_eq!(parse_document(&nested, Some(10)).err(), Some(1000));
+        assert!(parse_document(&nested, None).is_ok());
+
+        let text = format!("<p>{}</p>", "x".repeat(1000));
+        assert!(parse_document(&text, Some(10)).is_ok());
+        assert_eq!(parse_document("<p>a</p><p>b</p>", Some(2)).err(), Some(8));
+    }
+
+    #[tokio::test]
+    async fn test_min_content_length_skips_saving() {
+        let page = r#"<html><body><a href="/next">next</a> enough content</body></html>"#;
+        let address = serve(vec![
+            ("/", response("200 OK", &[], page)),
+            ("/pixel", response("200 OK", &[], "GIF89a")),
+        ])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let pixel = Url::from_str(&format!("{address}/pixel")).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("yoink-min-content-length-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut ctx = test_context("min-content-length", &base_url);
+        ctx.min_content_length = Some(page.len());
+        ctx.tracer = Tracer::new(Some(pixel.clone()));
+        ctx.sinks = Arc::new(vec![
+            OutputSink::new(OutputFormat::Html, &directory, &SaveOptions::default()).unwrap(),
+        ]);
+
+        crawl_url(ctx.clone(), base_url.clone()).await;
+        crawl_url(ctx.clone(), pixel.clone()).await;
+
+        let html = directory.join("html");
+        assert!(
+            base_url
+                .storage_path(&html, FilenameScheme::Encoded)
+                .exists()
+        );
+        assert!(!pixel.storage_path(&html, FilenameScheme::Encoded).exists());
+        assert!(
+            ctx.tracer
+                .events()
+                .contains(&TraceEvent::TooSmall { bytes: 6 })
+        );
+        let history = ctx.queue.lock().await.snapshot();
+        assert_eq!(history.processed.len(), 2);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_content_skips_later_copies() {
+        let page = r#"<html><body><a href="/next">next</a></body></html>"#;
+        let address = serve(vec![
+            ("/", response("200 OK", &[], page)),
+            ("/home", response("200 OK", &[], page)),
+        ])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let copy = Url::from_str(&format!("{address}/home")).unwrap();
+
+        let directory =
+            std::env::temp_dir().join(format!("yoink-dedupe-content-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut ctx = test_context("dedupe-content", &base_url);
+        ctx.content_dedupe = Some(ContentDedupe::default());
+        ctx.tracer = Tracer::new(Some(copy.clone()));
+        ctx.sinks = Arc::new(vec![
+            OutputSink::new(OutputFormat::Html, &directory, &SaveOptions::default()).unwrap(),
+        ]);
+
+        crawl_url(ctx.clone(), base_url.clone()).await;
+        crawl_url(ctx.clone(), copy.clone()).await;
+
+        let html = directory.join("html");
+        assert!(
+            base_url
+                .storage_path(&html, FilenameScheme::Encoded)
+                .exists()
+        );
+        assert!(!copy.storage_path(&html, FilenameScheme::Encoded).exists());
+        assert_eq!(
+            ctx.tracer.events().last(),
+            Some(&TraceEvent::DuplicateContent {
+                of: base_url.clone()
+            })
+        );
+
+        ctx.journal.flush_now().await;
+        let journal_path =
+            std::env::temp_dir().join(format!("yoink-dedupe-content-{}.log", std::process::id()));
+        let journal = std::fs::read_to_string(&journal_path).unwrap();
+        assert!(
+            journal.lines().any(|line| {
+                line.starts_with("duplicate;") && line.ends_with(&format!(";{copy}"))
+            })
+        );
+        assert_eq!(
+            Journal::load_history(journal_path).unwrap().processed.len(),
+            2
+        );
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dropped_links_counted_by_reason() {
+        let address = serve(vec![(
+            "/",
+            response(
+                "200 OK",
+                &[],
+                r#"<a href="/ok">ok</a><a href="mailto:team@example.com">mail</a>
+                <a href="tel:+123">call</a><a href="http://[::1/">broken</a>
+                <a href="http://example.com:99999/">port</a><a href="ftp://example.com/">ftp</a>"#,
+            ),
+        )])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+
+        let mut ctx = test_context("dropped-links", &base_url);
+        let dropped_links = DroppedLinks::new(false);
+        ctx.dropped_links = Some(dropped_links.clone());
+        crawl_url(ctx, base_url).await;
+
+        assert_eq!(
+            dropped_links.counts(),
+            vec![
+                (UrlError::UnsupportedScheme, 2),
+                (UrlError::InvalidHost, 1),
+                (UrlError::InvalidPort, 1),
+                (UrlError::UnexpectedFormat, 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_links_resolved_against_base_href() {
+        let address = serve(vec![(
+            "/docs/guide/intro",
+            response(
+                "200 OK",
+                &[],
+                r#"<html><head><base href="../../static/v2/"></head>
+                <body><a href="page.html">page</a><a href="/root">root</a></body></html>"#,
+            ),
+        )])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+        let url = Url::from_str(&format!("{address}/docs/guide/intro")).unwrap();
+
+        let ctx = test_context("base-href", &base_url);
+        crawl_url(ctx.clone(), url.clone()).await;
+        let mut queue = ctx.queue.lock().await;
+        let pending: Vec<String> = std::iter::from_fn(|| queue.next().map(|(url, _)| url))
+            .map(|url| url.to_string())
+            .collect();
+        assert_eq!(
+            pending,
+            vec![
+                base_url.to_string(),
+                format!("{address}/static/v2/page.html"),
+                format!("{address}/root"),
+            ]
+        );
+        drop(queue);
+
+        let mut ctx = test_context("base-href-ignored", &base_url);
+        ctx.ignore_base_href = true;
+        crawl_url(ctx.clone(), url).await;
+        let mut queue = ctx.queue.lock().await;
+        assert!(
+            std::iter::from_fn(|| queue.next().map(|(url, _)| url))
+                .any(|url| url.to_string() == format!("{address}/docs/guide/page.html"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_extra_hosts_enqueued_unless_excluded() {
+        let address = serve(vec![(
+            "/",
+            response(
+                "200 OK",
+                &[],
+                r#"<a href="https://assets.example.net/app.css">css</a>
+                <a href="https://assets.example.net/private/keys">keys</a>
+                <a href="https://cdn.example.net/app.js">js</a>"#,
+            ),
+        )])
+        .await;
+        let base_url = Url::from_str(&address).unwrap();
+
+        let mut ctx = test_context("extra-hosts", &base_url);
+        ctx.extra_hosts = Arc::new(vec!["assets.example.net".to_owned()]);
+        ctx.url_filter = Arc::new(UrlFilter {
+            exclude: vec![UrlPattern::from_str("path:/private/*").unwrap()],
+            ..UrlFilter::default()
+        });
+        crawl_url(ctx.clone(), base_url.clone()).await;
+        let mut queue = ctx.queue.lock().await;
+        let pending: Vec<String> = std::iter::from_fn(|| queue.next().map(|(url, _)| url))
+            .map(|url| url.to_string())
+            .collect();
+        assert_eq!(
+            pending,
+            vec![
+                base_url.to_string(),
+                "https://assets.example.net/app.css".to_owned(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_host_sample_caps_processed_pages() {
+        let page = |links: &str| response("200 OK", &[], links);
+        let address = serve(vec![
+            (
+                "/",
+                page(r#"<a href="/a">a</a><a href="/b">b</a><a href="/c">c</a>"#),
+            ),
+            ("/a", page("a")),
+            ("/b", page("b")),
+            ("/c", page("c")),
+        ])
+        .await;
+        let other_address = address.replace("127.0.0.1", "localhost");
+
+        let base_url = Url::from_str(&address).unwrap();
+        let mut ctx = test_context("host-sample", &base_url);
+        ctx.scope = Scope::Domain;
+        ctx.host_sample = Some(HostSample::new(2));
+
+        for address in [&address, &other_address] {
+            for path in ["", "/a", "/b", "/c"] {
+                let url = Url::from_str(&format!("{address}{path}")).unwrap();
+                crawl_url(ctx.clone(), url).await;
+            }
+        }
+
+        let history = ctx.queue.lock().await.snapshot();
+        let mut processed: Vec<String> = history
+            .processed
+            .iter()
+            .map(|(url, _)| url.to_string())
+            .collect();
+        processed.sort();
+        let mut expected = vec![
+            base_url.to_string(),
+            format!("{address}/a"),
+            other_address.clone(),
+            format!("{other_address}/a"),
+        ];
+        expected.sort();
+        assert_eq!(processed, expected);
+        assert_eq!(history.failed.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_links_enqueued_in_batches() {
+        let base_url = Url::from_str("https://example.com").unwrap();
+        let mut ctx = test_context("enqueue-batches", &base_url);
+        ctx.enqueue_batch_size = Some(2);
+        let links: Vec<Url> = (0..5)
+            .map(|i| Url::from_str(&format!("https://example.com/{i}")).unwrap())
+            .collect();
+
+        let queue = ctx.queue.clone();
+        let observer = tokio::spawn(async move {
+            let mut observed = Vec::new();
+            loop {
+                let pending = queue.lock().await.pending().count();
+                if observed.last() != Some(&pending) {
+                    observed.push(pending);
+                }
+                if pending == 6 {
+                    return observed;
+                }
+                yield_now().await;
+            }
+        });
+        yield_now().await;
+
+        ctx.enqueue_links(&base_url, &links).await;
+        assert_eq!(observer.await.unwrap(), vec![1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn test_content_type_allowlist() {
+        let html_only = vec!["text/html".to_owned()];
+        assert!(is_allowed_content_type(&html_only, "text/html"));
+        assert!(is_allowed_content_type(
+            &html_only,
+            "text/html; charset=utf-8"
+        ));
+        assert!(is_allowed_content_type(
+            &html_only,
+            "Text/HTML;charset=ISO-8859-1"
+        ));
+        assert!(!is_allowed_content_type(&html_only, "application/pdf"));
+        assert!(!is_allowed_content_type(&html_only, "image/png"));
+        assert!(!is_allowed_content_type(&html_only, "text/htmlx"));
+
+        let text = vec!["text/*".to_owned(), "application/xhtml+xml".to_owned()];
+        assert!(is_allowed_content_type(&text, "text/plain"));
+        assert!(is_allowed_content_type(&text, "application/xhtml+xml"));
+        assert!(!is_allowed_content_type(&text, "application/zip"));
+
+        assert!(is_allowed_content_type(&[], "application/zip"));
+    }
+
+    #[test]
+    fn test_decode_body_uses_charset() {
+        assert_eq!(decode_body("café".as_bytes(), None), "café");
+        assert_eq!(
+            decode_body(b"caf\xe9", Some("text/html; charset=\"ISO-8859-1\"")),
+            "café"
+        );
+        assert_eq!(
+            decode_body("café".as_bytes(), Some("text/html; charset=unknown")),
+            "café"
+        );
+    }
+
+    #[test]
+    fn test_extracted_links_report() {
+        let document = Html::parse_document(
+            r#"<a href="/docs">Docs</a><a href="guide.html">Guide</a>
+            <a href=" https://example.com/spaced ">Spaced</a><a>No href</a>"#,
+        );
+        let hrefs = extract_links(&document, &[LinkSelector::from_str("a").unwrap()]);
+        let url = Url::from_str("https://example.com/index").unwrap();
+
+        assert_eq!(
+            format_extracted_links(&url, &hrefs),
+            "Extracted 3 links from https://example.com/index:\n  /docs\n  guide.html\n   https://example.com/spaced \n"
+        );
+    }
+
+    #[test]
+    fn test_parse_srcset() {
+        assert_eq!(parse_srcset("a.jpg 1x, b.jpg 2x"), vec!["a.jpg", "b.jpg"]);
+        assert_eq!(
+            parse_srcset("  /img/small.png 480w,\n /img/large.png   1080w , /img/raw.png"),
+            vec!["/img/small.png", "/img/large.png", "/img/raw.png"]
+        );
+        assert_eq!(
+            parse_srcset("img,v1.jpg 1x,img,v2.jpg 2x"),
+            vec!["img,v1.jpg", "img,v2.jpg"]
+        );
+        assert_eq!(parse_srcset("a.jpg,b.jpg"), vec!["a.jpg,b.jpg"]);
+        assert_eq!(parse_srcset("a.jpg, b.jpg 2x"), vec!["a.jpg", "b.jpg"]);
+        assert_eq!(
+            parse_srcset("a.jpg (min-width: 1px, max-width: 2px), b.jpg"),
+            vec!["a.jpg", "b.jpg"]
+        );
+        assert!(parse_srcset(" , ").is_empty());
+
+        let document = Html::parse_document(
+            r#"<picture><source srcset="wide.webp 2x, narrow.webp 1x">
+            <img src="fallback.jpg" srcset="a.jpg 1x, b.jpg 2x"></picture>"#,
+        );
+        assert_eq!(
+            extract_srcset_urls(&document),
+            vec!["wide.webp", "narrow.webp", "a.jpg", "b.jpg"]
+        );
+    }
+
+    #[test]
+    fn test_extract_canonical() {
+        let document = Html::parse_document(
+            r#"<html><head><link rel="alternate" href="/feed"><link rel="canonical" href="/article"></head></html>"#,
+        );
+        assert_eq!(extract_canonical(&document), Some("/article".to_owned()));
+        assert_eq!(
+            extract_canonical(&Html::parse_document("<p>no canonical</p>")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_extract_links_from_json() {
+        let body = r#"{"data": [{"id": 1}], "next": "/api/items?page=2",
+            "_links": {"self": {"href": "/api/items"}, "next": {"href": "/api/items?cursor=abc"}}}"#;
+        let paths = vec!["next".to_owned(), "_links.next.href".to_owned()];
+
+        assert_eq!(
+            extract_links_from_json(body, &paths),
+            vec!["/api/items?page=2", "/api/items?cursor=abc"]
+        );
+        assert!(extract_links_from_json("not json", &paths).is_empty());
+
+        assert!(is_json_content_type("application/json"));
+        assert!(is_json_content_type("application/hal+json; charset=utf-8"));
+        assert!(!is_json_content_type("text/html"));
+    }
+
+    #[tokio::test]
+    async fn test_credentials_only_sent_to_seed_scheme_and_authority() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (requests_tx, mut requests_rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, request) = accept_request(&listener).await;
+                requests_tx.send(request).unwrap();
+                let _ = socket
+                    .write_all(response("200 OK", &[], "ok").as_bytes())
+                    .await;
+            }
+        });
+
+        let page = Url::from_str(&format!("http://127.0.0.1:{port}/page")).unwrap();
+        for (seed, sends_credentials) in [
+            (format!("http://127.0.0.1:{port}"), true),
+            (format!("https://127.0.0.1:{port}"), false),
+            (format!("http://localhost:{port}"), false),
+        ] {
+            let mut ctx = test_context("credentials", &Url::from_str(&seed).unwrap());
+            ctx.credentials = Some(Credentials {
+                username: "user".to_owned(),
+                password: "secret".to_owned(),
+            });
+            crawl_url(ctx, page.clone()).await;
+
+            let request = requests_rx.recv().await.unwrap().to_ascii_lowercase();
+            assert_eq!(
+                request.contains("authorization: basic dxnlcjpzzwnyzxq="),
+                sends_credentials,
+                "{seed}"
+            );
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use crate::{journal::JournalHistory, url::Url};
+
+pub struct CrawlDiff {
+    pub discovered: Vec<Url>,
+    pub failed: Vec<Url>,
+    pub processed: Vec<Url>,
+}
+
+impl CrawlDiff {
+    pub fn new(old: &JournalHistory, new: &JournalHistory) -> Self {
+        let old_known = known_urls(old);
+        let old_failed: HashSet<&Url> = old.failed.iter().collect();
+        let old_processed: HashSet<&Url> = old.processed.iter().map(|(url, _)| url).collect();
+
+        CrawlDiff {
+            discovered: sorted(
+                known_urls(new)
+                    .into_iter()
+                    .filter(|url| !old_known.contains(url)),
+            ),
+            failed: sorted(new.failed.iter().filter(|url| !old_failed.contains(url))),
+            processed: sorted(
+                new.processed
+                    .iter()
+                    .map(|(url, _)| url)
+                    .filter(|url| !old_processed.contains(url)),
+            ),
+        }
+    }
+
+    pub fn print(&self) {
+        for (label, urls) in [
+            ("Newly discovered", &self.discovered),
+            ("Newly failed", &self.failed),
+            ("Newly processed", &self.processed),
+        ] {
+            println!("{label}: {}", urls.len());
+            for url in urls {
+                println!("  {url}");
+            }
+        }
+    }
+}
+
+fn known_urls(history: &JournalHistory) -> HashSet<&Url> {
+    history
+        .pending
+        .iter()
+        .chain(history.processing.iter())
+        .chain(history.processed.iter().map(|(url, _)| url))
+        .chain(history.failed.iter())
+        .collect()
+}
+
+fn sorted<'a>(urls: impl Iterator<Item = &'a Url>) -> Vec<Url> {
+    let mut urls: Vec<Url> = urls.cloned().collect();
+    urls.sort_by_key(|url| url.to_string());
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn url(path: &str) -> Url {
+        Url::from_str(&format!("https://example.com/{path}")).unwrap()
+    }
+
+    #[test]
+    fn test_diff_categories() {
+        let old = JournalHistory {
+            pending: vec![url("queued")],
+            processing: vec![url("in-flight")],
+            processed: vec![(url("home"), 100), (url("flaky"), 100)],
+            failed: vec![url("broken")],
+            ..JournalHistory::default()
+        };
+        let new = JournalHistory {
+            pending: vec![url("fresh")],
+            processing: vec![],
+            processed: vec![(url("home"), 200), (url("queued"), 200), (url("new"), 200)],
+            failed: vec![url("broken"), url("flaky"), url("gone")],
+            ..JournalHistory::default()
+        };
+
+        let diff = CrawlDiff::new(&old, &new);
+        assert_eq!(diff.discovered, vec![url("fresh"), url("gone"), url("new")]);
+        assert_eq!(diff.failed, vec![url("flaky"), url("gone")]);
+        assert_eq!(diff.processed, vec![url("new"), url("queued")]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use reqwest::{
+    Client,
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::ACCEPT,
+};
+use tokio::sync::Semaphore;
+
+use crate::json::JsonValue;
+
+const RECORD_TYPE_A: f64 = 1.0;
+const RECORD_TYPE_AAAA: f64 = 28.0;
+
+#[derive(Clone)]
+pub struct DohResolver {
+    client: Client,
+    endpoint: Arc<String>,
+}
+
+impl DohResolver {
+    pub fn new(endpoint: String) -> Self {
+        DohResolver {
+            client: Client::new(),
+            endpoint: Arc::new(endpoint),
+        }
+    }
+
+    async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let mut addresses = Vec::new();
+
+        for record_type in ["A", "AAAA"] {
+            let body = self
+                .client
+                .get(self.endpoint.as_str())
+                .query(&[("name", host), ("type", record_type)])
+                .header(ACCEPT, "application/dns-json")
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|err| err.to_string())?
+                .text()
+                .await
+                .map_err(|err| err.to_string())?;
+
+            addresses.extend(parse_doh_response(&body)?);
+        }
+
+        Ok(addresses)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+
+        Box::pin(async move {
+            let host = name.as_str();
+            let addresses = match resolver.lookup(host).await {
+                Ok(addresses) if !addresses.is_empty() => addresses,
+                Ok(_) => system_lookup(host).await?,
+                Err(err) => {
+                    eprintln!("DNS over HTTPS lookup failed for {host}, falling back: {err}");
+                    system_lookup(host).await?
+                }
+            };
+
+            let addrs: Addrs = Box::new(
+                addresses
+                    .into_iter()
+                    .map(|address| SocketAddr::new(address, 0)),
+            );
+            Ok(addrs)
+        })
+    }
+}
+
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = system_lookup(name.as_str()).await?;
+            let addrs: Addrs = Box::new(
+                addresses
+                    .into_iter()
+                    .map(|address| SocketAddr::new(address, 0)),
+            );
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct LimitedResolver {
+    inner: Arc<dyn Resolve>,
+    permits: Arc<Semaphore>,
+}
+
+impl LimitedResolver {
+    pub fn new(inner: Arc<dyn Resolve>, max_concurrent: usize) -> Self {
+        LimitedResolver {
+            inner,
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+        }
+    }
+}
+
+impl Resolve for LimitedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+
+        Box::pin(async move {
+            let _permit = resolver.permits.acquire_owned().await?;
+            resolver.inner.resolve(name).await
+        })
+    }
+}
+
+async fn system_lookup(
+    host: &str,
+) -> Result<Vec<IpAddr>, Box<dyn std::error::Error + Send + Sync>> {
+    let addresses = tokio::net::lookup_host((host, 0)).await?;
+    Ok(addresses.map(|address| address.ip()).collect())
+}
+
+pub fn parse_doh_response(body: &str) -> Result<Vec<IpAddr>, String> {
+    let response = JsonValue::parse(body).map_err(|err| err.to_string())?;
+
+    let Some(answers) = response.get("Answer").and_then(|answer| answer.as_array()) else {
+        return Ok(Vec::new());
+    };
+
+    Ok(answers
+        .iter()
+        .filter(|answer| {
+            answer
+                .get("type")
+                .and_then(|record_type| record_type.as_f64())
+                .is_some_and(|record_type| {
+                    record_type == RECORD_TYPE_A || record_type == RECORD_TYPE_AAAA
+                })
+        })
+        .filter_map(|answer| answer.get("data")?.as_str()?.parse().ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        str::FromStr,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::{task::JoinSet, time::sleep};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct StubResolver {
+        in_flight: Arc<AtomicUsize>,
+        max_in_flight: Arc<AtomicUsize>,
+    }
+
+    impl Resolve for StubResolver {
+        fn resolve(&self, _name: Name) -> Resolving {
+            let in_flight = self.in_flight.clone();
+            let max_in_flight = self.max_in_flight.clone();
+
+            Box::pin(async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                sleep(Duration::from_millis(20)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                let addrs: Addrs = Box::new(std::iter::once(SocketAddr::from(([127, 0, 0, 1], 0))));
+                Ok(addrs)
+            })
+        }
+    }
+
+    #[test]
+    fn test_parse_doh_response() {
+        let body = r#"{
+            "Status": 0,
+            "Question": [{"name": "example.com.", "type": 1}],
+            "Answer": [
+                {"name": "example.com.", "type": 5, "TTL": 300, "data": "alias.example.com."},
+                {"name": "alias.example.com.", "type": 1, "TTL": 300, "data": "93.184.216.34"},
+                {"name": "alias.example.com.", "type": 28, "TTL": 300, "data": "2606:2800:220:1::248"}
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_doh_response(body).unwrap(),
+            vec![
+                "93.184.216.34".parse::<IpAddr>().unwrap(),
+                "2606:2800:220:1::248".parse::<IpAddr>().unwrap(),
+            ]
+        );
+        assert!(parse_doh_response(r#"{"Status": 3}"#).unwrap().is_empty());
+        assert!(parse_doh_response("not json").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_limited_resolver_caps_concurrent_lookups() {
+        let stub = StubResolver::default();
+        let max_in_flight = stub.max_in_flight.clone();
+        let resolver = LimitedResolver::new(Arc::new(stub), 2);
+
+        let mut lookups = JoinSet::new();
+        for i in 0..8 {
+            let name = Name::from_str(&format!("host{i}.example.com")).unwrap();
+            lookups.spawn(resolver.resolve(name));
+        }
+        while let Some(result) = lookups.join_next().await {
+            assert!(result.unwrap().is_ok());
+        }
+
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,58 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::url::{Url, UrlError};
+
+#[derive(Clone, Default)]
+pub struct DroppedLinks {
+    log: bool,
+    counts: Arc<Mutex<HashMap<UrlError, usize>>>,
+}
+
+impl DroppedLinks {
+    pub fn new(log: bool) -> Self {
+        DroppedLinks {
+            log,
+            counts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn record(&self, from: &Url, href: &str, err: &UrlError) {
+        if self.log {
+            eprintln!("Dropped link {href:?} on {from}: {err}");
+        }
+
+        *self
+            .counts
+            .lock()
+            .expect("Dropped links lock poisoned")
+            .entry(err.clone())
+            .or_default() += 1;
+    }
+
+    pub fn counts(&self) -> Vec<(UrlError, usize)> {
+        let mut counts: Vec<(UrlError, usize)> = self
+            .counts
+            .lock()
+            .expect("Dropped links lock poisoned")
+            .iter()
+            .map(|(err, count)| (err.clone(), *count))
+            .collect();
+        counts.sort_by(|(a_err, a), (b_err, b)| {
+            b.cmp(a)
+                .then_with(|| a_err.to_string().cmp(&b_err.to_string()))
+        });
+        counts
+    }
+
+    pub fn print_report(&self) {
+        let counts = self.counts();
+        let total: usize = counts.iter().map(|(_, count)| count).sum();
+        println!("Dropped links: {total}");
+        for (err, count) in counts {
+            println!("  {count} {err}");
+        }
+    }
+}
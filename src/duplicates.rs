@@ -0,0 +1,160 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::{encoding::fnv1a_hash, url::Url};
+
+#[derive(Clone, Default)]
+pub struct DuplicateContent {
+    urls_by_hash: Arc<Mutex<HashMap<u64, Vec<Url>>>>,
+}
+
+impl DuplicateContent {
+    pub fn record(&self, url: &Url, body: &str) {
+        self.urls_by_hash
+            .lock()
+            .expect("Duplicate content lock poisoned")
+            .entry(fnv1a_hash(body.as_bytes()))
+            .or_default()
+            .push(url.to_owned());
+    }
+
+    pub fn groups(&self) -> Vec<Vec<Url>> {
+        let urls_by_hash = self
+            .urls_by_hash
+            .lock()
+            .expect("Duplicate content lock poisoned");
+        let mut groups: Vec<Vec<Url>> = urls_by_hash
+            .values()
+            .filter(|urls| urls.len() > 1)
+            .map(|urls| {
+                let mut urls = urls.clone();
+                urls.sort_by_key(|url| url.to_string());
+                urls
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            b.len()
+                .cmp(&a.len())
+                .then_with(|| a[0].to_string().cmp(&b[0].to_string()))
+        });
+        groups
+    }
+
+    pub fn print_report(&self) {
+        let groups = self.groups();
+        let duplicated: usize = groups.iter().map(|urls| urls.len()).sum();
+        println!(
+            "Duplicate content: {} groups covering {duplicated} urls",
+            groups.len()
+        );
+        for urls in groups {
+            println!("{} identical pages:", urls.len());
+            for url in urls {
+                println!("  {url}");
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ContentDedupe {
+    first_url_by_hash: Arc<Mutex<HashMap<u64, Url>>>,
+}
+
+impl ContentDedupe {
+    pub fn original(&self, url: &Url, body: &[u8]) -> Option<Url> {
+        let mut first_url_by_hash = self
+            .first_url_by_hash
+            .lock()
+            .expect("Content dedupe lock poisoned");
+        let first = first_url_by_hash
+            .entry(fnv1a_hash(body))
+            .or_insert_with(|| url.to_owned());
+
+        (first != url).then(|| first.to_owned())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TitleDedupe {
+    first_url_by_title: Arc<Mutex<HashMap<String, Url>>>,
+}
+
+impl TitleDedupe {
+    pub fn original(&self, url: &Url, title: &str) -> Option<Url> {
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        if title.is_empty() {
+            return None;
+        }
+
+        let mut first_url_by_title = self
+            .first_url_by_title
+            .lock()
+            .expect("Title dedupe lock poisoned");
+        let first = first_url_by_title
+            .entry(title)
+            .or_insert_with(|| url.to_owned());
+
+        (first != url).then(|| first.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_groups_identical_bodies() {
+        let duplicates = DuplicateContent::default();
+        let page = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        duplicates.record(&page("a"), "<p>home</p>");
+        duplicates.record(&page("unique"), "<p>only once</p>");
+        duplicates.record(&page("b"), "<p>home</p>");
+        duplicates.record(&page("x?print=1"), "<p>article</p>");
+        duplicates.record(&page("c"), "<p>home</p>");
+        duplicates.record(&page("x"), "<p>article</p>");
+
+        assert_eq!(
+            duplicates.groups(),
+            vec![
+                vec![page("a"), page("b"), page("c")],
+                vec![page("x"), page("x?print=1")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_keeps_first_copy() {
+        let dedupe = ContentDedupe::default();
+        let page = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        assert_eq!(dedupe.original(&page("a"), b"<p>home</p>"), None);
+        assert_eq!(dedupe.original(&page("b"), b"<p>other</p>"), None);
+        assert_eq!(
+            dedupe.original(&page("a?sid=1"), b"<p>home</p>"),
+            Some(page("a"))
+        );
+        assert_eq!(dedupe.original(&page("a"), b"<p>home</p>"), None);
+    }
+
+    #[test]
+    fn test_title_dedupe_keeps_first_page() {
+        let dedupe = TitleDedupe::default();
+        let page = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        assert_eq!(dedupe.original(&page("a"), "Home | Example"), None);
+        assert_eq!(dedupe.original(&page("b"), "About | Example"), None);
+        assert_eq!(
+            dedupe.original(&page("tag/rust"), "  Home |\n  Example "),
+            Some(page("a"))
+        );
+        assert_eq!(dedupe.original(&page("a"), "Home | Example"), None);
+        assert_eq!(dedupe.original(&page("c"), " "), None);
+        assert_eq!(dedupe.original(&page("d"), ""), None);
+    }
+}
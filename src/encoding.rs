@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 pub fn url_encode(val: &str) -> String {
     val.bytes()
         .map(|b| match b {
@@ -9,10 +11,157 @@ pub fn url_encode(val: &str) -> String {
         .collect()
 }
 
-#[allow(unused)]
+pub fn url_decode(val: &str) -> String {
+    let bytes = val.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = bytes.get(i + 1..i + 3)
+            && let Ok(hex) = std::str::from_utf8(hex)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+            continue;
+        }
+
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn encode_control_chars(val: &str) -> Cow<'_, str> {
+    if !val.chars().any(char::is_control) {
+        return Cow::Borrowed(val);
+    }
+
+    let mut out = String::with_capacity(val.len());
+    for ch in val.chars() {
+        if ch.is_control() {
+            let mut buf = [0; 4];
+            for b in ch.encode_utf8(&mut buf).bytes() {
+                out.push_str(&format!("%{b:02X}"));
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+
+    Cow::Owned(out)
+}
+
+const PUNYCODE_BASE: u32 = 36;
+const PUNYCODE_TMIN: u32 = 1;
+const PUNYCODE_TMAX: u32 = 26;
+const PUNYCODE_SKEW: u32 = 38;
+const PUNYCODE_DAMP: u32 = 700;
+const PUNYCODE_INITIAL_BIAS: u32 = 72;
+const PUNYCODE_INITIAL_N: u32 = 128;
+
+pub fn punycode_encode(input: &str) -> Option<String> {
+    let code_points: Vec<u32> = input.chars().map(|c| c as u32).collect();
+    let mut output: String = input.chars().filter(char::is_ascii).collect();
+
+    let basic_count = output.len();
+    let mut handled = basic_count;
+    if basic_count > 0 {
+        output.push('-');
+    }
+
+    let mut n = PUNYCODE_INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = PUNYCODE_INITIAL_BIAS;
+
+    while handled < code_points.len() {
+        let m = code_points
+            .iter()
+            .copied()
+            .filter(|&c| c >= n)
+            .min()
+            .unwrap_or(n);
+        delta = (m - n)
+            .checked_mul(handled as u32 + 1)?
+            .checked_add(delta)?;
+        n = m;
+
+        for &c in &code_points {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c != n {
+                continue;
+            }
+
+            let mut q = delta;
+            let mut k = PUNYCODE_BASE;
+            loop {
+                let t = if k <= bias {
+                    PUNYCODE_TMIN
+                } else if k >= bias + PUNYCODE_TMAX {
+                    PUNYCODE_TMAX
+                } else {
+                    k - bias
+                };
+                if q < t {
+                    break;
+                }
+                output.push(punycode_digit(t + (q - t) % (PUNYCODE_BASE - t)));
+                q = (q - t) / (PUNYCODE_BASE - t);
+                k += PUNYCODE_BASE;
+            }
+            output.push(punycode_digit(q));
+
+            bias = punycode_adapt(delta, handled as u32 + 1, handled == basic_count);
+            delta = 0;
+            handled += 1;
+        }
+
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+
+    Some(output)
+}
+
+fn punycode_digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+fn punycode_adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+    let mut delta = if first_time {
+        delta / PUNYCODE_DAMP
+    } else {
+        delta / 2
+    };
+    delta += delta / num_points;
+
+    let mut k = 0;
+    while delta > ((PUNYCODE_BASE - PUNYCODE_TMIN) * PUNYCODE_TMAX) / 2 {
+        delta /= PUNYCODE_BASE - PUNYCODE_TMIN;
+        k += PUNYCODE_BASE;
+    }
+
+    k + (PUNYCODE_BASE - PUNYCODE_TMIN + 1) * delta / (delta + PUNYCODE_SKEW)
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+pub fn fnv1a_hash(input: &[u8]) -> u64 {
+    input.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
 const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
 
-#[allow(unused)]
 pub fn base64_encode(input: &[u8]) -> String {
     let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
 
@@ -72,7 +221,7 @@ pub fn base64_decode(input: &str) -> Result<Vec<u8>, DecodeError> {
         return Ok(Vec::new());
     }
 
-    if clean.len() % 4 != 0 {
+    if !clean.len().is_multiple_of(4) {
         return Err(DecodeError::Length);
     }
 
@@ -140,6 +289,16 @@ fn val_from_base64_char(c: char) -> Option<u8> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_encode_control_chars() {
+        assert!(matches!(
+            encode_control_chars("plain/path"),
+            Cow::Borrowed("plain/path")
+        ));
+        assert_eq!(encode_control_chars("a\nb\rc\x00d"), "a%0Ab%0Dc%00d");
+        assert_eq!(encode_control_chars("x\u{85}y"), "x%C2%85y");
+    }
+
     #[test]
     fn encode_examples() {
         assert_eq!(base64_encode(b""), "");
@@ -151,6 +310,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn url_decode_examples() {
+        assert_eq!(url_decode("p%40ss%3Aword"), "p@ss:word");
+        assert_eq!(url_decode("caf%C3%A9"), "café");
+        assert_eq!(url_decode("100%"), "100%");
+        assert_eq!(url_decode("%zz"), "%zz");
+        assert_eq!(url_decode(&url_encode("a b/c")), "a b/c");
+    }
+
+    #[test]
+    fn punycode_examples() {
+        assert_eq!(punycode_encode("münchen"), Some("mnchen-3ya".to_owned()));
+        assert_eq!(punycode_encode("bücher"), Some("bcher-kva".to_owned()));
+        assert_eq!(punycode_encode("例え"), Some("r8jz45g".to_owned()));
+        assert_eq!(punycode_encode("テスト"), Some("zckzah".to_owned()));
+        assert_eq!(
+            punycode_encode(&format!("{}\u{10FFFF}", "a".repeat(5000))),
+            None
+        );
+    }
+
+    #[test]
+    fn fnv1a_examples() {
+        assert_eq!(fnv1a_hash(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_hash(b"foobar"), 0x85944171f73967e8);
+    }
+
     #[test]
     fn round_trip_examples() {
         let cases: [&[u8]; 4] = [b"", b"Glados", b"Chell", b"The cake is a lie."];
@@ -0,0 +1,194 @@
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Component {
+    Url,
+    Host,
+    Path,
+    Query,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlPattern {
+    component: Component,
+    glob: String,
+}
+
+impl FromStr for UrlPattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (component, glob) = match value.split_once(':') {
+            Some(("host", glob)) => (Component::Host, glob),
+            Some(("path", glob)) => (Component::Path, glob),
+            Some(("query", glob)) => (Component::Query, glob),
+            _ => (Component::Url, value),
+        };
+        if glob.is_empty() {
+            return Err(format!("empty pattern in {value:?}"));
+        }
+
+        Ok(UrlPattern {
+            component,
+            glob: glob.to_owned(),
+        })
+    }
+}
+
+impl UrlPattern {
+    pub fn matches(&self, url: &Url) -> bool {
+        match self.component {
+            Component::Url => glob_matches(&self.glob, &url.to_string()),
+            Component::Host => glob_matches(&self.glob.to_ascii_lowercase(), &url.host),
+            Component::Path => glob_matches(
+                &self.glob,
+                &format!("/{}", url.path.as_deref().unwrap_or_default()),
+            ),
+            Component::Query => glob_matches(&self.glob, url.query.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UrlFilter {
+    pub include: Vec<UrlPattern>,
+    pub exclude: Vec<UrlPattern>,
+    pub include_patterns: Vec<Regex>,
+    pub exclude_patterns: Vec<Regex>,
+}
+
+impl UrlFilter {
+    pub fn allows(&self, url: &Url) -> bool {
+        let text = url.to_string();
+        let included = (self.include.is_empty() && self.include_patterns.is_empty())
+            || self.include.iter().any(|pattern| pattern.matches(url))
+            || self
+                .include_patterns
+                .iter()
+                .any(|regex| regex.is_match(&text));
+        let excluded = self.exclude.iter().any(|pattern| pattern.matches(url))
+            || self
+                .exclude_patterns
+                .iter()
+                .any(|regex| regex.is_match(&text));
+
+        included && !excluded
+    }
+}
+
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let mut parts = glob.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, url: &str) -> bool {
+        UrlPattern::from_str(pattern)
+            .unwrap()
+            .matches(&Url::from_str(url).unwrap())
+    }
+
+    #[test]
+    fn test_host_patterns() {
+        assert!(matches("host:example.com", "https://example.com/docs"));
+        assert!(matches("host:*.example.com", "https://api.example.com/v1"));
+        assert!(!matches("host:*.example.com", "https://example.com/v1"));
+        assert!(matches("host:*.Example.com", "https://CDN.example.com"));
+        assert!(!matches(
+            "host:example.com",
+            "https://example.com.evil.org/"
+        ));
+        assert!(!matches("host:docs*", "https://example.com/docs"));
+    }
+
+    #[test]
+    fn test_path_and_query_patterns() {
+        assert!(matches("path:/docs/*", "https://example.com/docs/intro"));
+        assert!(!matches("path:/docs/*", "https://docs.example.com/intro"));
+        assert!(!matches(
+            "path:/docs/*",
+            "https://example.com/blog?ref=/docs/x"
+        ));
+        assert!(matches("path:/", "https://example.com"));
+        assert!(matches("path:*.pdf", "https://example.com/files/a.pdf"));
+        assert!(!matches("path:*.pdf", "https://example.com/a.pdf.html"));
+
+        assert!(matches(
+            "query:*page=*",
+            "https://example.com/list?sort=asc&page=2"
+        ));
+        assert!(!matches("query:*page=*", "https://example.com/page=2"));
+
+        assert!(matches(
+            "https://example.com/*",
+            "https://example.com/a?b=c"
+        ));
+        assert!(UrlPattern::from_str("path:").is_err());
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let filter = UrlFilter {
+            include: vec![UrlPattern::from_str("path:/docs/*").unwrap()],
+            exclude: vec![UrlPattern::from_str("path:/docs/private/*").unwrap()],
+            ..UrlFilter::default()
+        };
+        let allows = |url: &str| filter.allows(&Url::from_str(url).unwrap());
+
+        assert!(allows("https://example.com/docs/guide"));
+        assert!(!allows("https://example.com/docs/private/keys"));
+        assert!(!allows("https://example.com/blog"));
+        assert!(UrlFilter::default().allows(&Url::from_str("https://example.com").unwrap()));
+    }
+
+    #[test]
+    fn test_regex_include_and_exclude() {
+        let filter = UrlFilter {
+            include_patterns: vec![Regex::new(r"^https://example\.com/docs/").unwrap()],
+            exclude_patterns: vec![
+                Regex::new(r"[?&]sort=").unwrap(),
+                Regex::new(r"/search$").unwrap(),
+            ],
+            ..UrlFilter::default()
+        };
+        let allows = |url: &str| filter.allows(&Url::from_str(url).unwrap());
+
+        assert!(allows("https://example.com/docs/guide"));
+        assert!(allows("https://example.com/docs/list?page=2"));
+        assert!(!allows("https://example.com/docs/list?page=2&sort=asc"));
+        assert!(!allows("https://example.com/docs/search"));
+        assert!(!allows("https://example.com/blog"));
+
+        let mixed = UrlFilter {
+            include: vec![UrlPattern::from_str("path:/blog/*").unwrap()],
+            include_patterns: vec![Regex::new("/docs/").unwrap()],
+            ..UrlFilter::default()
+        };
+        assert!(mixed.allows(&Url::from_str("https://example.com/blog/post").unwrap()));
+        assert!(mixed.allows(&Url::from_str("https://example.com/docs/guide").unwrap()));
+        assert!(!mixed.allows(&Url::from_str("https://example.com/about").unwrap()));
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use crate::{
+    scope::{Scope, ScopeRule, in_scope},
+    url::{FragmentPolicy, Url},
+};
+
+pub fn read_url_list(
+    path: &Path,
+    base_url: &Url,
+    scope: Scope,
+    extra_hosts: &[String],
+    scope_rules: &[ScopeRule],
+    fragments: FragmentPolicy,
+) -> Result<Vec<Url>, String> {
+    let f = std::fs::File::open(path).map_err(|err| err.to_string())?;
+    let reader = std::io::BufReader::new(f);
+
+    let mut urls = Vec::new();
+    for line in reader.lines() {
+        let line = line.map_err(|err| err.to_string())?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if !line.starts_with('/') && !line.contains("://") {
+            eprintln!(
+                "Skipping url {line} from {}: not an absolute url or path",
+                path.display()
+            );
+            continue;
+        }
+
+        match Url::resolve(base_url, line, fragments) {
+            Ok(url) if in_scope(base_url, &url, scope, extra_hosts, scope_rules) => urls.push(url),
+            Ok(_) => eprintln!("Skipping out of scope url {line} from {}", path.display()),
+            Err(err) => eprintln!("Skipping url {line} from {}: {err}", path.display()),
+        }
+    }
+
+    Ok(urls)
+}
+
+pub fn write_url_list<'a>(path: &Path, urls: impl Iterator<Item = &'a Url>) -> Result<(), String> {
+    let f = std::fs::File::create(path).map_err(|err| err.to_string())?;
+    let mut writer = std::io::BufWriter::new(f);
+
+    for url in urls {
+        writeln!(writer, "{url}").map_err(|err| err.to_string())?;
+    }
+
+    writer.flush().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::queue::Queue;
+
+    #[test]
+    fn test_frontier_pre_populated_from_url_list() {
+        let path = std::env::temp_dir().join(format!("yoink-head-start-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "https://example.com/a\n\n/b\nhttps://other.com/c\nnot a url\n",
+        )
+        .unwrap();
+
+        let base = Url::from_str("https://example.com").unwrap();
+        let urls =
+            read_url_list(&path, &base, Scope::Host, &[], &[], FragmentPolicy::Strip).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        for url in &urls {
+            queue.add_pending(url);
+        }
+
+        assert_eq!(queue.next(), Some((base, 0)));
+        assert_eq!(queue.next().unwrap().0.to_string(), "https://example.com/a");
+        assert_eq!(queue.next().unwrap().0.to_string(), "https://example.com/b");
+        assert_eq!(queue.next(), None);
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let path = std::env::temp_dir().join(format!("yoink-frontier-{}", std::process::id()));
+        let base = Url::from_str("https://example.com").unwrap();
+        let urls = vec![
+            Url::from_str("https://example.com").unwrap(),
+            Url::from_str("https://example.com/a").unwrap(),
+            Url::from_str("https://sub.example.com/b/c").unwrap(),
+        ];
+
+        write_url_list(&path, urls.iter()).unwrap();
+        let imported =
+            read_url_list(&path, &base, Scope::Domain, &[], &[], FragmentPolicy::Strip).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(imported, urls);
+    }
+}
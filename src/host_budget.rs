@@ -0,0 +1,85 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Clone)]
+pub struct HostBudget {
+    limit: Duration,
+    spent: Arc<Mutex<HashMap<String, Duration>>>,
+}
+
+impl HostBudget {
+    pub fn new(limit: Duration) -> Self {
+        HostBudget {
+            limit,
+            spent: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn record(&self, host: &str, elapsed: Duration) {
+        let mut spent = self.spent.lock().expect("Host budget lock poisoned");
+        *spent.entry(host.to_owned()).or_default() += elapsed;
+    }
+
+    pub fn spent(&self, host: &str) -> Duration {
+        self.spent
+            .lock()
+            .expect("Host budget lock poisoned")
+            .get(host)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn is_exhausted(&self, host: &str) -> bool {
+        self.spent(host) >= self.limit
+    }
+
+    pub fn start(&self, host: &str) -> HostTimer {
+        HostTimer {
+            budget: self.clone(),
+            host: host.to_owned(),
+            started: Instant::now(),
+        }
+    }
+}
+
+pub struct HostTimer {
+    budget: HostBudget,
+    host: String,
+    started: Instant,
+}
+
+impl Drop for HostTimer {
+    fn drop(&mut self) {
+        self.budget.record(&self.host, self.started.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_time_accounting_and_cutoff() {
+        let budget = HostBudget::new(Duration::from_secs(10));
+
+        budget.record("slow.example.com", Duration::from_secs(4));
+        budget.record("fast.example.com", Duration::from_secs(1));
+        budget.record("slow.example.com", Duration::from_secs(5));
+        assert_eq!(budget.spent("slow.example.com"), Duration::from_secs(9));
+        assert!(!budget.is_exhausted("slow.example.com"));
+
+        budget.record("slow.example.com", Duration::from_secs(1));
+        assert!(budget.is_exhausted("slow.example.com"));
+        assert!(!budget.is_exhausted("fast.example.com"));
+        assert!(!budget.is_exhausted("unseen.example.com"));
+
+        let before = budget.spent("fast.example.com");
+        let timer = budget.start("fast.example.com");
+        std::thread::sleep(Duration::from_millis(10));
+        drop(timer);
+        assert!(budget.spent("fast.example.com") >= before + Duration::from_millis(10));
+    }
+}
@@ -0,0 +1,60 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::time::{Instant, sleep_until};
+
+#[derive(Clone)]
+pub struct HostInterval {
+    interval: Duration,
+    next_slot: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl HostInterval {
+    pub fn new(interval: Duration) -> Self {
+        HostInterval {
+            interval,
+            next_slot: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn reserve(&self, host: &str, now: Instant) -> Instant {
+        let mut next_slot = self.next_slot.lock().expect("Host interval lock poisoned");
+        let next = next_slot.entry(host.to_owned()).or_insert(now);
+        let slot = (*next).max(now);
+        *next = slot + self.interval;
+
+        slot
+    }
+
+    pub async fn tick(&self, host: &str) {
+        let slot = self.reserve(host, Instant::now());
+        sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hosts_spaced_independently() {
+        let host_interval = HostInterval::new(Duration::from_millis(500));
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+
+        assert_eq!(host_interval.reserve("a.example.com", now), now);
+        assert_eq!(host_interval.reserve("a.example.com", now), now + ms(500));
+        assert_eq!(host_interval.reserve("b.example.com", now), now);
+        assert_eq!(
+            host_interval.reserve("a.example.com", now + ms(100)),
+            now + ms(1000)
+        );
+        assert_eq!(
+            host_interval.reserve("b.example.com", now + ms(2000)),
+            now + ms(2000)
+        );
+    }
+}
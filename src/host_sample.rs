@@ -0,0 +1,57 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone)]
+pub struct HostSample {
+    limit: usize,
+    fetched: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl HostSample {
+    pub fn new(limit: usize) -> Self {
+        HostSample {
+            limit,
+            fetched: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn claim(&self, host: &str) -> bool {
+        let mut fetched = self.fetched.lock().expect("Host sample lock poisoned");
+        let count = fetched.entry(host.to_owned()).or_default();
+        if *count >= self.limit {
+            return false;
+        }
+
+        *count += 1;
+        true
+    }
+
+    pub fn is_full(&self, host: &str) -> bool {
+        self.fetched
+            .lock()
+            .expect("Host sample lock poisoned")
+            .get(host)
+            .is_some_and(|count| *count >= self.limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claims_capped_per_host() {
+        let sample = HostSample::new(2);
+
+        assert!(sample.claim("a.example.com"));
+        assert!(!sample.is_full("a.example.com"));
+        assert!(sample.claim("a.example.com"));
+        assert!(sample.is_full("a.example.com"));
+        assert!(!sample.claim("a.example.com"));
+
+        assert!(!sample.is_full("b.example.com"));
+        assert!(sample.claim("b.example.com"));
+    }
+}
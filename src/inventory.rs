@@ -0,0 +1,67 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use crate::url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Discovery {
+    pub depth: u32,
+    pub first_seen: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct Discoveries {
+    by_url: Arc<Mutex<HashMap<Url, Discovery>>>,
+}
+
+impl Discoveries {
+    pub fn record(&self, url: &Url, from: Option<&Url>, now: u64) {
+        let mut by_url = self.by_url.lock().expect("Discoveries lock poisoned");
+        let depth = from
+            .and_then(|from| by_url.get(from))
+            .map_or(0, |from| from.depth + 1);
+
+        by_url.entry(url.to_owned()).or_insert(Discovery {
+            depth,
+            first_seen: now,
+        });
+    }
+
+    pub fn get(&self, url: &Url) -> Option<Discovery> {
+        self.by_url
+            .lock()
+            .expect("Discoveries lock poisoned")
+            .get(url)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_depth_follows_first_discovery() {
+        let discoveries = Discoveries::default();
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        discoveries.record(&url(""), None, 10);
+        discoveries.record(&url("a"), Some(&url("")), 11);
+        discoveries.record(&url("b"), Some(&url("a")), 12);
+        discoveries.record(&url("b"), Some(&url("")), 13);
+
+        assert_eq!(
+            discoveries.get(&url("b")),
+            Some(Discovery {
+                depth: 2,
+                first_seen: 12
+            })
+        );
+        assert_eq!(discoveries.get(&url("a")).unwrap().depth, 1);
+        assert_eq!(discoveries.get(&url("missing")), None);
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, net::IpAddr, str::FromStr, sync::Arc};
+
+use reqwest::dns::{Name, Resolve};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+pub struct IpLimiter {
+    resolver: Arc<dyn Resolve>,
+    max_concurrent: usize,
+    addresses: Mutex<HashMap<String, Option<IpAddr>>>,
+    slots: Mutex<HashMap<IpAddr, Arc<Semaphore>>>,
+}
+
+impl IpLimiter {
+    pub fn new(resolver: Arc<dyn Resolve>, max_concurrent: usize) -> Self {
+        IpLimiter {
+            resolver,
+            max_concurrent,
+            addresses: Mutex::new(HashMap::new()),
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn acquire(&self, host: &str) -> Option<OwnedSemaphorePermit> {
+        let address = self.address(host).await?;
+        let slot = self
+            .slots
+            .lock()
+            .await
+            .entry(address)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrent)))
+            .clone();
+
+        slot.acquire_owned().await.ok()
+    }
+
+    async fn address(&self, host: &str) -> Option<IpAddr> {
+        let name = host_without_port(host);
+        if let Some(address) = self.addresses.lock().await.get(name) {
+            return *address;
+        }
+
+        let address = match Name::from_str(name) {
+            Ok(dns_name) => match self.resolver.resolve(dns_name).await {
+                Ok(mut addrs) => addrs.next().map(|addr| addr.ip()),
+                Err(err) => {
+                    eprintln!("Failed to resolve {name}, not limiting it per ip: {err}");
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        self.addresses.lock().await.insert(name.to_owned(), address);
+        address
+    }
+}
+
+fn host_without_port(host: &str) -> &str {
+    if let Some(bracketed) = host.strip_prefix('[') {
+        return bracketed.split(']').next().unwrap_or(bracketed);
+    }
+
+    match host.rsplit_once(':') {
+        Some((name, port)) if port.bytes().all(|b| b.is_ascii_digit()) => name,
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use reqwest::dns::{Addrs, Resolving};
+    use tokio::time::timeout;
+
+    use super::*;
+
+    struct StubResolver {
+        addresses: HashMap<&'static str, IpAddr>,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl Resolve for StubResolver {
+        fn resolve(&self, name: Name) -> Resolving {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            let address = self.addresses.get(name.as_str()).copied();
+
+            Box::pin(async move {
+                let addrs: Addrs = Box::new(address.map(|ip| SocketAddr::new(ip, 0)).into_iter());
+                Ok(addrs)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hosts_sharing_an_ip_share_a_limit() {
+        let shared: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let resolver = StubResolver {
+            addresses: HashMap::from([
+                ("a.example.com", shared),
+                ("b.example.com", shared),
+                ("c.example.com", other),
+            ]),
+            lookups: lookups.clone(),
+        };
+        let limiter = IpLimiter::new(Arc::new(resolver), 1);
+        let blocked = Duration::from_millis(50);
+
+        let first = limiter.acquire("a.example.com").await;
+        assert!(first.is_some());
+        assert!(
+            timeout(blocked, limiter.acquire("b.example.com:8080"))
+                .await
+                .is_err()
+        );
+        assert!(limiter.acquire("c.example.com").await.is_some());
+        assert!(limiter.acquire("unknown.example.com").await.is_none());
+
+        drop(first);
+        let second = timeout(blocked, limiter.acquire("b.example.com")).await;
+        assert!(second.unwrap().is_some());
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn test_host_without_port() {
+        assert_eq!(host_without_port("example.com"), "example.com");
+        assert_eq!(host_without_port("example.com:8080"), "example.com");
+        assert_eq!(host_without_port("[::1]:8080"), "::1");
+    }
+}
@@ -1,32 +1,238 @@
-use std::{collections::HashSet, fmt, io::BufRead, path::PathBuf, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::{self, BufRead, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use tokio::{fs, io::AsyncWriteExt, sync::mpsc};
+use flate2::{Compression, bufread::GzDecoder, read::MultiGzDecoder, write::GzEncoder};
+use tokio::{
+    fs,
+    io::AsyncWriteExt,
+    sync::{mpsc, oneshot},
+    time::{self, Interval, MissedTickBehavior},
+};
 
-use crate::url::Url;
+use crate::{encoding::encode_control_chars, refresh::now_secs, url::Url};
+
+enum JournalMessage {
+    Entry(JournalEntry),
+    Flush(oneshot::Sender<u64>),
+}
 
 pub enum JournalEntry {
-    Pending { url: Url },
-    Processing { url: Url },
-    Processed { url: Url },
-    Failed { url: Url },
+    Pending {
+        url: Url,
+        at: u64,
+    },
+    Processing {
+        url: Url,
+        at: u64,
+    },
+    Processed {
+        url: Url,
+        at: u64,
+        status: Option<u16>,
+    },
+    Failed {
+        url: Url,
+        at: u64,
+        status: Option<u16>,
+    },
+    Probed {
+        url: Url,
+        status: u16,
+        at: u64,
+    },
+    Retry {
+        url: Url,
+        attempt: u32,
+        at: u64,
+    },
+    Duplicate {
+        url: Url,
+        at: u64,
+    },
 }
 
+const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 0x08];
+
 #[derive(Default)]
 pub struct JournalHistory {
     pub pending: Vec<Url>,
     pub processing: Vec<Url>,
-    pub processed: Vec<Url>,
+    pub processed: Vec<(Url, u64)>,
     pub failed: Vec<Url>,
+    pub attempts: HashMap<Url, u32>,
+    pub statuses: HashMap<Url, u16>,
+    pub failed_at: HashMap<Url, u64>,
+}
+
+impl JournalHistory {
+    pub fn merge(&mut self, other: JournalHistory, now: u64) -> Vec<JournalEntry> {
+        let mut entries = Vec::new();
+        let mut statuses = std::mem::take(&mut self.statuses);
+        let mut failed_at = std::mem::take(&mut self.failed_at);
+
+        let mut processed: HashMap<Url, u64> =
+            std::mem::take(&mut self.processed).into_iter().collect();
+        for (url, at) in other.processed {
+            if processed.get(&url).is_some_and(|existing| *existing >= at) {
+                continue;
+            }
+            processed.insert(url.clone(), at);
+            let status = other.statuses.get(&url).copied();
+            match status {
+                Some(status) => statuses.insert(url.clone(), status),
+                None => statuses.remove(&url),
+            };
+            entries.push(JournalEntry::Processed { url, at, status });
+        }
+
+        let mut failed = HashSet::new();
+        for url in std::mem::take(&mut self.failed) {
+            if !processed.contains_key(&url) {
+                failed.insert(url);
+            }
+        }
+        for url in other.failed {
+            if !processed.contains_key(&url) && failed.insert(url.clone()) {
+                let at = other.failed_at.get(&url).copied().unwrap_or(now);
+                let status = other.statuses.get(&url).copied();
+                failed_at.insert(url.clone(), at);
+                if let Some(status) = status {
+                    statuses.insert(url.clone(), status);
+                }
+                entries.push(JournalEntry::Failed { url, at, status });
+            }
+        }
+
+        let is_done = |url: &Url| processed.contains_key(url) || failed.contains(url);
+        let mut processing = HashSet::new();
+        for url in std::mem::take(&mut self.processing) {
+            if !is_done(&url) {
+                processing.insert(url);
+            }
+        }
+        for url in other.processing {
+            if !is_done(&url) && processing.insert(url.clone()) {
+                entries.push(JournalEntry::Processing { url, at: now });
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut pending = Vec::new();
+        for url in std::mem::take(&mut self.pending) {
+            if !is_done(&url) && !processing.contains(&url) && seen.insert(url.clone()) {
+                pending.push(url);
+            }
+        }
+        for url in other.pending {
+            if !is_done(&url) && !processing.contains(&url) && seen.insert(url.clone()) {
+                pending.push(url.clone());
+                entries.push(JournalEntry::Pending { url, at: now });
+            }
+        }
+
+        let mut attempts: HashMap<Url, u32> = std::mem::take(&mut self.attempts)
+            .into_iter()
+            .filter(|(url, _)| !is_done(url))
+            .collect();
+        for (url, attempt) in other.attempts {
+            if is_done(&url)
+                || attempts
+                    .get(&url)
+                    .is_some_and(|existing| *existing >= attempt)
+            {
+                continue;
+            }
+            attempts.insert(url.clone(), attempt);
+            entries.push(JournalEntry::Retry {
+                url,
+                attempt,
+                at: now,
+            });
+        }
+
+        statuses.retain(|url, _| is_done(url));
+        failed_at.retain(|url, _| failed.contains(url));
+
+        self.pending = pending;
+        self.processing = processing.into_iter().collect();
+        self.processed = processed.into_iter().collect();
+        self.failed = failed.into_iter().collect();
+        self.attempts = attempts;
+        self.statuses = statuses;
+        self.failed_at = failed_at;
+
+        entries
+    }
+
+    pub fn entries(&self, at: u64) -> Vec<JournalEntry> {
+        let pending = self.pending.iter().map(|url| JournalEntry::Pending {
+            url: url.clone(),
+            at,
+        });
+        let processing = self.processing.iter().map(|url| JournalEntry::Processing {
+            url: url.clone(),
+            at,
+        });
+        let processed = self
+            .processed
+            .iter()
+            .map(|(url, at)| JournalEntry::Processed {
+                url: url.clone(),
+                at: *at,
+                status: self.statuses.get(url).copied(),
+            });
+        let failed = self.failed.iter().map(|url| JournalEntry::Failed {
+            url: url.clone(),
+            at: self.failed_at.get(url).copied().unwrap_or(at),
+            status: self.statuses.get(url).copied(),
+        });
+        let attempts = self
+            .attempts
+            .iter()
+            .map(|(url, attempt)| JournalEntry::Retry {
+                url: url.clone(),
+                attempt: *attempt,
+                at,
+            });
+
+        pending
+            .chain(processing)
+            .chain(processed)
+            .chain(failed)
+            .chain(attempts)
+            .collect()
+    }
 }
 
 impl fmt::Display for JournalEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            JournalEntry::Pending { url } => write!(f, "pending;{url}"),
-            JournalEntry::Processing { url } => write!(f, "processing;{url}"),
-            JournalEntry::Processed { url } => write!(f, "processed;{url}"),
-            JournalEntry::Failed { url } => write!(f, "failed;{url}"),
-        }
+        let line = match self {
+            JournalEntry::Pending { url, at } => format!("pending;{at};{url}"),
+            JournalEntry::Processing { url, at } => format!("processing;{at};{url}"),
+            JournalEntry::Processed {
+                url,
+                at,
+                status: Some(status),
+            } => format!("processed;{at};{status};{url}"),
+            JournalEntry::Processed { url, at, .. } => format!("processed;{at};{url}"),
+            JournalEntry::Failed {
+                url,
+                at,
+                status: Some(status),
+            } => format!("failed;{at};{status};{url}"),
+            JournalEntry::Failed { url, at, .. } => format!("failed;{at};{url}"),
+            JournalEntry::Probed { url, status, at } => format!("probed;{at};{status};{url}"),
+            JournalEntry::Retry { url, attempt, at } => format!("retry;{at};{attempt};{url}"),
+            JournalEntry::Duplicate { url, at } => format!("duplicate;{at};{url}"),
+        };
+
+        f.write_str(&encode_control_chars(&line))
     }
 }
 
@@ -34,81 +240,381 @@ impl FromStr for JournalEntry {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (status, url) = s.split_once(';').ok_or("invalid entry".to_owned())?;
+        let (kind, rest) = s.split_once(';').ok_or("invalid entry".to_owned())?;
+        let (fields, url) = split_numeric_fields(rest);
         let url = Url::from_str(url).map_err(|err| err.to_string())?;
+        let field = |index: usize| fields.get(index).copied();
+        let at = field(0).unwrap_or(0);
+        let status = field(1).and_then(|status| u16::try_from(status).ok());
 
-        match status {
-            "pending" => Ok(JournalEntry::Pending { url }),
-            "processing" => Ok(JournalEntry::Processing { url }),
-            "processed" => Ok(JournalEntry::Processed { url }),
-            "failed" => Ok(JournalEntry::Failed { url }),
+        match kind {
+            "pending" => Ok(JournalEntry::Pending { url, at }),
+            "processing" => Ok(JournalEntry::Processing { url, at }),
+            "processed" => Ok(JournalEntry::Processed { url, at, status }),
+            "duplicate" => Ok(JournalEntry::Duplicate { url, at }),
+            "failed" => Ok(JournalEntry::Failed { url, at, status }),
+            "probed" => {
+                // Older journals wrote probes as `probed;{status};{at}`
+                let (at, status) = match (field(0), field(1)) {
+                    (Some(at), Some(status)) if (100..=599).contains(&status) => (at, status),
+                    (Some(status), at) => (at.unwrap_or(0), status),
+                    (None, _) => return Err("invalid probe status".to_owned()),
+                };
+                let status =
+                    u16::try_from(status).map_err(|_| "invalid probe status".to_owned())?;
+                Ok(JournalEntry::Probed { url, status, at })
+            }
+            "retry" => {
+                let (at, attempt) = match (field(0), field(1)) {
+                    (Some(at), Some(attempt)) => (at, attempt),
+                    (Some(attempt), None) => (0, attempt),
+                    (None, _) => return Err("invalid retry attempt".to_owned()),
+                };
+                let attempt =
+                    u32::try_from(attempt).map_err(|_| "invalid retry attempt".to_owned())?;
+                Ok(JournalEntry::Retry { url, attempt, at })
+            }
             _ => Err("invalid status".to_owned()),
         }
     }
 }
 
+fn split_numeric_fields(mut s: &str) -> (Vec<u64>, &str) {
+    let mut fields = Vec::new();
+    while let Some((field, rest)) = s.split_once(';')
+        && let Ok(field) = field.parse()
+    {
+        fields.push(field);
+        s = rest;
+    }
+
+    (fields, s)
+}
+
+struct JournalWriter {
+    file: fs::File,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+    offset: u64,
+}
+
+impl JournalWriter {
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        match &mut self.encoder {
+            Some(encoder) => {
+                encoder.write_all(bytes)?;
+                let compressed = std::mem::take(encoder.get_mut());
+                self.file.write_all(&compressed).await?;
+            }
+            None => self.file.write_all(bytes).await?,
+        }
+
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        if let Some(encoder) = &mut self.encoder {
+            encoder.flush()?;
+            let compressed = std::mem::take(encoder.get_mut());
+            self.file.write_all(&compressed).await?;
+        }
+        self.file.flush().await
+    }
+
+    async fn finish(mut self) -> io::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            let compressed = encoder.finish()?;
+            self.file.write_all(&compressed).await?;
+        }
+        self.file.flush().await
+    }
+}
+
+fn replace_journal(path: &Path, content: &[u8]) -> Result<(), String> {
+    let content = if is_compressed(path) {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(content).map_err(|err| err.to_string())?;
+        encoder.finish().map_err(|err| err.to_string())?
+    } else {
+        content.to_vec()
+    };
+
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut file = std::fs::File::create(&temporary).map_err(|err| err.to_string())?;
+    file.write_all(&content).map_err(|err| err.to_string())?;
+    file.sync_all().map_err(|err| err.to_string())?;
+    std::fs::rename(&temporary, path).map_err(|err| err.to_string())
+}
+
+fn repair_compressed(path: &Path) -> Result<(), String> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(format!(
+                "failed to read journal file {}: {err}",
+                path.display()
+            ));
+        }
+    };
+    let (content, complete) = decode_members(&data);
+    if complete {
+        return Ok(());
+    }
+
+    eprintln!(
+        "Journal {} ends with an unfinished gzip member, rewriting it before appending",
+        path.display()
+    );
+    replace_journal(path, &content)
+}
+
+fn decode_members(mut data: &[u8]) -> (Vec<u8>, bool) {
+    let mut content = Vec::new();
+    let mut complete = true;
+
+    while !data.is_empty() {
+        let mut member = Vec::new();
+        let mut decoder = GzDecoder::new(data);
+        if decoder.read_to_end(&mut member).is_ok() {
+            content.extend(member);
+            data = decoder.into_inner();
+            continue;
+        }
+
+        // Decode the unfinished member on its own so the next member is not read as its data
+        complete = false;
+        let next = data[1..]
+            .windows(GZIP_MAGIC.len())
+            .position(|magic| magic == GZIP_MAGIC)
+            .map(|start| start + 1);
+        let mut member = Vec::new();
+        let _ = GzDecoder::new(&data[..next.unwrap_or(data.len())]).read_to_end(&mut member);
+        let lines_end = member
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |i| i + 1);
+        content.extend(&member[..lines_end]);
+
+        match next {
+            Some(next) => data = &data[next..],
+            None => break,
+        }
+    }
+
+    (content, complete)
+}
+
+async fn next_tick(ticks: &mut Option<Interval>) {
+    match ticks {
+        Some(ticks) => {
+            ticks.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "gz")
+}
+
+fn open_reader(path: &Path) -> Result<Option<Box<dyn BufRead>>, String> {
+    let f = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(format!(
+                "failed to read journal file {}: {err}",
+                path.display()
+            ));
+        }
+    };
+
+    if is_compressed(path) {
+        Ok(Some(Box::new(io::BufReader::new(MultiGzDecoder::new(f)))))
+    } else {
+        Ok(Some(Box::new(io::BufReader::new(f))))
+    }
+}
+
+fn uncompressed_len(path: &Path) -> Result<u64, String> {
+    let Some(mut reader) = open_reader(path)? else {
+        return Ok(0);
+    };
+
+    let mut len = 0;
+    loop {
+        match reader.fill_buf() {
+            Ok([]) | Err(_) => return Ok(len),
+            Ok(buf) => {
+                let read = buf.len();
+                len += read as u64;
+                reader.consume(read);
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Journal {
-    sender: mpsc::UnboundedSender<JournalEntry>,
+    sender: mpsc::UnboundedSender<JournalMessage>,
 }
 
 impl Journal {
-    pub fn new(path: PathBuf) -> (Self, impl Future<Output = ()>) {
-        let (tx, mut rx) = mpsc::unbounded_channel::<JournalEntry>();
+    pub fn new(
+        path: PathBuf,
+        flush_interval: Option<Duration>,
+    ) -> Result<(Self, impl Future<Output = ()>), String> {
+        if is_compressed(&path) {
+            repair_compressed(&path)?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|err| format!("failed to open journal file {}: {err}", path.display()))?;
+        let offset = uncompressed_len(&path)?;
+        let (tx, mut rx) = mpsc::unbounded_channel::<JournalMessage>();
 
         let task = async move {
-            let mut f = fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(path)
-                .await
-                .expect("Failed to create journal file");
-
-            while let Some(entry) = rx.recv().await {
-                let line = format!("{entry}\n");
-                if let Err(err) = f.write_all(line.as_bytes()).await {
-                    eprintln!("Failed to write journal entry to the file: {err}");
+            let encoder =
+                is_compressed(&path).then(|| GzEncoder::new(Vec::new(), Compression::default()));
+            let mut writer = JournalWriter {
+                file: fs::File::from_std(file),
+                encoder,
+                offset,
+            };
+
+            let mut flush_ticks = flush_interval.map(|period| {
+                let mut ticks = time::interval(period);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ticks
+            });
+            let mut unflushed = false;
+
+            loop {
+                let message = tokio::select! {
+                    message = rx.recv() => message,
+                    _ = next_tick(&mut flush_ticks), if unflushed => {
+                        if let Err(err) = writer.flush().await {
+                            eprintln!("Failed to flush the journal: {err}");
+                        }
+                        unflushed = false;
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    break;
+                };
+
+                match message {
+                    JournalMessage::Entry(entry) => {
+                        let line = format!("{entry}\n");
+                        if let Err(err) = writer.write(line.as_bytes()).await {
+                            eprintln!("Failed to write journal entry to the file: {err}");
+                        }
+                        unflushed = true;
+                    }
+                    JournalMessage::Flush(ack) => {
+                        if let Err(err) = writer.flush().await {
+                            eprintln!("Failed to flush the journal: {err}");
+                        }
+                        unflushed = false;
+                        let _ = ack.send(writer.offset);
+                    }
                 }
             }
 
-            if let Err(err) = f.flush().await {
+            if let Err(err) = writer.finish().await {
                 eprintln!("Failed to flush the journal: {err}");
             }
         };
 
-        (Journal { sender: tx }, task)
+        Ok((Journal { sender: tx }, task))
     }
 
     pub fn send(&mut self, entry: JournalEntry) {
-        if let Err(err) = self.sender.send(entry) {
+        if let Err(err) = self.sender.send(JournalMessage::Entry(entry)) {
             eprintln!("Failed to send journal entry: {err}");
         }
     }
 
-    pub fn load_history(path: PathBuf) -> JournalHistory {
-        let f = match std::fs::File::open(path) {
-            Ok(f) => f,
-            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
-                return JournalHistory::default();
-            }
-            Err(err) => {
-                panic!("failed to read journal file {err}")
+    pub async fn flush_now(&self) -> Option<u64> {
+        let (tx, rx) = oneshot::channel();
+        if self.sender.send(JournalMessage::Flush(tx)).is_err() {
+            eprintln!("Failed to request journal flush: journal task is gone");
+            return None;
+        }
+
+        match rx.await {
+            Ok(offset) => Some(offset),
+            Err(_) => {
+                eprintln!("Journal task stopped before flushing");
+                None
             }
-        };
-        let reader = std::io::BufReader::new(f);
+        }
+    }
 
-        let mut maybe_pending = Vec::new();
-        let mut maybe_processing = HashSet::new();
-        let mut processed = HashSet::new();
-        let mut failed = HashSet::new();
+    pub fn compact(path: &Path, history: &JournalHistory) -> Result<usize, String> {
+        let entries = history.entries(now_secs());
+        let mut content = Vec::new();
+        for entry in &entries {
+            writeln!(content, "{entry}").map_err(|err| err.to_string())?;
+        }
+        replace_journal(path, &content)?;
+
+        Ok(entries.len())
+    }
+
+    pub fn load_history(path: PathBuf) -> Result<JournalHistory, String> {
+        Journal::load_history_from(path, JournalHistory::default(), 0)
+    }
+
+    pub fn load_history_from(
+        path: PathBuf,
+        base: JournalHistory,
+        offset: u64,
+    ) -> Result<JournalHistory, String> {
+        let mut maybe_pending = base.pending;
+        let mut maybe_processing: HashSet<Url> = base.processing.into_iter().collect();
+        let mut processed: HashMap<Url, u64> = base.processed.into_iter().collect();
+        let mut failed: HashSet<Url> = base.failed.into_iter().collect();
+        let mut attempts = base.attempts;
+        let mut statuses = base.statuses;
+        let mut failed_at = base.failed_at;
+
+        let Some(mut reader) = open_reader(&path)? else {
+            return Ok(JournalHistory {
+                pending: maybe_pending,
+                processing: maybe_processing.into_iter().collect(),
+                processed: processed.into_iter().collect(),
+                failed: failed.into_iter().collect(),
+                attempts,
+                statuses,
+                failed_at,
+            });
+        };
+        if let Err(err) = io::copy(&mut reader.by_ref().take(offset), &mut io::sink()) {
+            eprintln!("failed to skip to journal offset {offset}: {err}");
+        }
 
         for line in reader.lines() {
             let line = match line {
                 Ok(l) => l,
-                Err(err) => {
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    eprintln!("journal ends with a truncated entry: {err}");
+                    break;
+                }
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
                     eprintln!("failed to read journal line: {err}");
                     continue;
                 }
+                Err(err) => {
+                    eprintln!("failed to read journal: {err}");
+                    break;
+                }
             };
 
             let entry = match JournalEntry::from_str(&line) {
@@ -120,41 +626,609 @@ impl Journal {
             };
 
             match entry {
-                JournalEntry::Pending { url } => {
+                JournalEntry::Pending { url, .. } => {
                     maybe_pending.push(url);
                 }
-                JournalEntry::Processing { url } => {
+                JournalEntry::Processing { url, .. } => {
                     maybe_processing.insert(url);
                 }
-                JournalEntry::Processed { url } => {
-                    processed.insert(url);
+                JournalEntry::Processed { url, at, status } => {
+                    match status {
+                        Some(status) => statuses.insert(url.clone(), status),
+                        None => statuses.remove(&url),
+                    };
+                    processed.insert(url, at);
+                }
+                JournalEntry::Probed { url, at, status } => {
+                    statuses.insert(url.clone(), status);
+                    processed.insert(url, at);
                 }
-                JournalEntry::Failed { url } => {
+                JournalEntry::Duplicate { url, at } => {
+                    statuses.remove(&url);
+                    processed.insert(url, at);
+                }
+                JournalEntry::Failed { url, at, status } => {
+                    match status {
+                        Some(status) => statuses.insert(url.clone(), status),
+                        None => statuses.remove(&url),
+                    };
+                    failed_at.insert(url.clone(), at);
                     failed.insert(url);
                 }
+                JournalEntry::Retry { url, attempt, .. } => {
+                    attempts.insert(url, attempt);
+                }
             }
         }
 
+        let mut seen = HashSet::new();
         let pending: Vec<_> = maybe_pending
             .into_iter()
             .filter(|entry| {
                 !maybe_processing.contains(entry)
-                    && !processed.contains(entry)
+                    && !processed.contains_key(entry)
                     && !failed.contains(entry)
+                    && seen.insert(entry.clone())
             })
             .collect();
         let processing: Vec<_> = maybe_processing
             .into_iter()
-            .filter(|entry| !processed.contains(entry) && !failed.contains(entry))
+            .filter(|entry| !processed.contains_key(entry) && !failed.contains(entry))
             .collect();
+        attempts.retain(|url, _| !processed.contains_key(url) && !failed.contains(url));
+        statuses.retain(|url, _| processed.contains_key(url) || failed.contains(url));
+        failed_at.retain(|url, _| failed.contains(url));
         let processed: Vec<_> = processed.into_iter().collect();
         let failed: Vec<_> = failed.into_iter().collect();
 
-        JournalHistory {
+        Ok(JournalHistory {
             pending,
             processing,
             processed,
             failed,
+            attempts,
+            statuses,
+            failed_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::Queue;
+
+    #[tokio::test]
+    async fn test_flush_now_persists_prior_entries() {
+        let path = std::env::temp_dir().join(format!("yoink-flush-now-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+
+        let a = Url::from_str("https://example.com/a").unwrap();
+        let b = Url::from_str("https://example.com/b").unwrap();
+        journal.send(JournalEntry::Pending {
+            url: a.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Processing {
+            url: a.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Pending {
+            url: b.clone(),
+            at: 0,
+        });
+        journal.flush_now().await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "pending;0;https://example.com/a\nprocessing;0;https://example.com/a\npending;0;https://example.com/b\n"
+        );
+
+        drop(journal);
+        journal_handle.await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_compressed_journal_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "yoink-compressed-journal-{}.log.gz",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let a = Url::from_str("https://example.com/a").unwrap();
+        let b = Url::from_str("https://example.com/b").unwrap();
+        let c = Url::from_str("https://example.com/c").unwrap();
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: a.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Pending {
+            url: b.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Processing {
+            url: a.clone(),
+            at: 0,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Processed {
+            url: a.clone(),
+            at: 1_700_000_000,
+            status: None,
+        });
+        journal.send(JournalEntry::Pending {
+            url: c.clone(),
+            at: 0,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let raw = std::fs::read(&path).unwrap();
+        assert_eq!(&raw[..2], &[0x1f, 0x8b]);
+
+        let history = Journal::load_history(path.clone()).unwrap();
+        assert_eq!(history.pending, vec![b, c]);
+        assert!(history.processing.is_empty());
+        assert_eq!(history.processed, vec![(a, 1_700_000_000)]);
+        assert!(history.failed.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_entry_metadata_round_trip() {
+        let url = Url::from_str("https://example.com/a;b").unwrap();
+        let entries = [
+            JournalEntry::Pending {
+                url: url.clone(),
+                at: 1_700_000_000,
+            },
+            JournalEntry::Processing {
+                url: url.clone(),
+                at: 1_700_000_001,
+            },
+            JournalEntry::Processed {
+                url: url.clone(),
+                at: 1_700_000_002,
+                status: Some(200),
+            },
+            JournalEntry::Processed {
+                url: url.clone(),
+                at: 1_700_000_003,
+                status: None,
+            },
+            JournalEntry::Failed {
+                url: url.clone(),
+                at: 1_700_000_004,
+                status: Some(404),
+            },
+            JournalEntry::Failed {
+                url: url.clone(),
+                at: 1_700_000_005,
+                status: None,
+            },
+            JournalEntry::Probed {
+                url: url.clone(),
+                status: 301,
+                at: 1_700_000_006,
+            },
+            JournalEntry::Retry {
+                url: url.clone(),
+                attempt: 2,
+                at: 1_700_000_007,
+            },
+            JournalEntry::Duplicate {
+                url: url.clone(),
+                at: 1_700_000_008,
+            },
+        ];
+        let lines: Vec<String> = entries.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "pending;1700000000;https://example.com/a;b",
+                "processing;1700000001;https://example.com/a;b",
+                "processed;1700000002;200;https://example.com/a;b",
+                "processed;1700000003;https://example.com/a;b",
+                "failed;1700000004;404;https://example.com/a;b",
+                "failed;1700000005;https://example.com/a;b",
+                "probed;1700000006;301;https://example.com/a;b",
+                "retry;1700000007;2;https://example.com/a;b",
+                "duplicate;1700000008;https://example.com/a;b",
+            ]
+        );
+        for line in &lines {
+            assert_eq!(&JournalEntry::from_str(line).unwrap().to_string(), line);
         }
     }
+
+    #[test]
+    fn test_legacy_entries() {
+        let url = Url::from_str("https://example.com/a").unwrap();
+        let parse = |line: &str| JournalEntry::from_str(line).unwrap();
+
+        assert!(matches!(
+            parse("pending;https://example.com/a"),
+            JournalEntry::Pending { url: parsed, at: 0 } if parsed == url
+        ));
+        assert!(matches!(
+            parse("processed;1700000000;https://example.com/a"),
+            JournalEntry::Processed { url: parsed, at: 1_700_000_000, status: None } if parsed == url
+        ));
+        assert!(matches!(
+            parse("processed;https://example.com/a"),
+            JournalEntry::Processed { url: parsed, at: 0, status: None } if parsed == url
+        ));
+        assert!(matches!(
+            parse("failed;https://example.com/a"),
+            JournalEntry::Failed { url: parsed, at: 0, status: None } if parsed == url
+        ));
+        assert!(matches!(
+            parse("retry;2;https://example.com/a"),
+            JournalEntry::Retry { url: parsed, attempt: 2, at: 0 } if parsed == url
+        ));
+        assert!(matches!(
+            parse("probed;301;1700000000;https://example.com/a"),
+            JournalEntry::Probed { url: parsed, status: 301, at: 1_700_000_000 } if parsed == url
+        ));
+        assert!(JournalEntry::from_str("probed;https://example.com/a").is_err());
+        assert!(JournalEntry::from_str("skipped;https://example.com/a").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_control_characters_never_split_entries() {
+        let path = std::env::temp_dir().join(format!("yoink-control-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let parsed = Url::from_str("https://example.com/a%0Ab\u{7f}").unwrap();
+        let constructed = Url {
+            path: Some("c\nfailed;https://example.com/d".to_owned()),
+            ..parsed.clone()
+        };
+        assert_eq!(
+            JournalEntry::Pending {
+                url: constructed.clone(),
+                at: 0,
+            }
+            .to_string(),
+            "pending;0;https://example.com/c%0Afailed;https://example.com/d"
+        );
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: parsed.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Pending {
+            url: constructed,
+            at: 0,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+
+        let history = Journal::load_history(path.clone()).unwrap();
+        assert_eq!(history.pending.len(), 2);
+        assert_eq!(history.pending[0], parsed);
+        assert_eq!(
+            history.pending[1].path.as_deref(),
+            Some("c%0Afailed;https://example.com/d")
+        );
+        assert!(history.failed.is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_merge_histories() {
+        let dir = std::env::temp_dir();
+        let first = dir.join(format!("yoink-merge-first-{}.log", std::process::id()));
+        let second = dir.join(format!("yoink-merge-second-{}.log", std::process::id()));
+        std::fs::write(
+            &first,
+            "pending;https://example.com/a\npending;https://example.com/b\n\
+             pending;https://example.com/c\npending;https://example.com/d\n\
+             processed;100;https://example.com/a\nfailed;https://example.com/b\n\
+             processing;https://example.com/c\n",
+        )
+        .unwrap();
+        std::fs::write(
+            &second,
+            "pending;https://example.com/a\npending;https://example.com/b\n\
+             pending;https://example.com/c\npending;https://example.com/e\n\
+             processed;50;https://example.com/a\nprocessed;200;https://example.com/b\n\
+             failed;150;503;https://example.com/c\n",
+        )
+        .unwrap();
+
+        let mut history = Journal::load_history(first.clone()).unwrap();
+        let entries = history.merge(
+            Journal::load_history(second.clone()).unwrap(),
+            1_700_000_000,
+        );
+        std::fs::remove_file(&first).unwrap();
+        std::fs::remove_file(&second).unwrap();
+
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+        let mut processed = history.processed.clone();
+        processed.sort_by_key(|(_, at)| *at);
+        assert_eq!(processed, vec![(url("a"), 100), (url("b"), 200)]);
+        assert!(history.processing.is_empty());
+        assert_eq!(history.failed, vec![url("c")]);
+        assert_eq!(history.pending, vec![url("d"), url("e")]);
+
+        assert_eq!(
+            entries.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec![
+                "processed;200;https://example.com/b",
+                "failed;150;503;https://example.com/c",
+                "pending;1700000000;https://example.com/e",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_history_from_offset() {
+        let path = std::env::temp_dir().join(format!("yoink-offset-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let a = Url::from_str("https://example.com/a").unwrap();
+        let b = Url::from_str("https://example.com/b").unwrap();
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: a.clone(),
+            at: 0,
+        });
+        let offset = journal.flush_now().await.unwrap();
+        assert_eq!(offset, std::fs::metadata(&path).unwrap().len());
+
+        journal.send(JournalEntry::Pending {
+            url: b.clone(),
+            at: 0,
+        });
+        journal.send(JournalEntry::Failed {
+            url: a.clone(),
+            at: 0,
+            status: None,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let base = JournalHistory {
+            processing: vec![a.clone()],
+            ..JournalHistory::default()
+        };
+        let history = Journal::load_history_from(path.clone(), base, offset).unwrap();
+        assert_eq!(history.pending, vec![b]);
+        assert!(history.processing.is_empty());
+        assert_eq!(history.failed, vec![a]);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unwritable_journal_is_an_error() {
+        let directory =
+            std::env::temp_dir().join(format!("yoink-journal-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        assert!(Journal::new(directory.clone(), None).is_err());
+        assert!(Journal::new(directory.join("missing").join("journal.log"), None).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_journal_is_an_error() {
+        let file = std::env::temp_dir().join(format!("yoink-journal-file-{}", std::process::id()));
+        std::fs::write(&file, "").unwrap();
+
+        let missing = file.with_extension("missing");
+        assert!(Journal::load_history(missing).unwrap().pending.is_empty());
+        let err = Journal::load_history(file.join("journal.log"))
+            .err()
+            .unwrap();
+        assert!(err.starts_with("failed to read journal file"));
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts_restored() {
+        let path = std::env::temp_dir().join(format!("yoink-retries-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let a = Url::from_str("https://example.com/a").unwrap();
+        let b = Url::from_str("https://example.com/b").unwrap();
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        for url in [&a, &b] {
+            journal.send(JournalEntry::Processing {
+                url: url.clone(),
+                at: 0,
+            });
+            journal.send(JournalEntry::Retry {
+                url: url.clone(),
+                attempt: 1,
+                at: 0,
+            });
+        }
+        journal.send(JournalEntry::Retry {
+            url: a.clone(),
+            attempt: 2,
+            at: 0,
+        });
+        journal.send(JournalEntry::Processed {
+            url: b.clone(),
+            at: 1_700_000_000,
+            status: None,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let history = Journal::load_history(path.clone()).unwrap();
+        assert_eq!(history.attempts, HashMap::from([(a.clone(), 2)]));
+
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], history.processing, vec![], vec![]);
+        queue.restore_attempts(history.attempts);
+        assert_eq!(queue.attempts(&a), 2);
+        assert_eq!(queue.attempts(&b), 0);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_compact_keeps_terminal_states() {
+        for name in ["yoink-compact.log", "yoink-compact.log.gz"] {
+            let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+            let mut temporary = path.as_os_str().to_owned();
+            temporary.push(".tmp");
+            std::fs::write(&temporary, "left over from a crashed compaction").unwrap();
+
+            let lines = "pending;1;https://example.com/a\npending;1;https://example.com/b\n\
+                         processing;2;https://example.com/a\nprocessed;3;200;https://example.com/a\n\
+                         pending;4;https://example.com/c\nprocessing;5;https://example.com/c\n\
+                         retry;6;1;https://example.com/c\nretry;7;2;https://example.com/c\n\
+                         pending;8;https://example.com/d\nfailed;9;404;https://example.com/d\n\
+                         pending;10;https://example.com/b\n";
+            let content = if is_compressed(&path) {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(lines.as_bytes()).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                lines.as_bytes().to_vec()
+            };
+            std::fs::write(&path, content).unwrap();
+
+            let history = Journal::load_history(path.clone()).unwrap();
+            assert_eq!(Journal::compact(&path, &history).unwrap(), 5);
+            assert!(!PathBuf::from(&temporary).exists());
+
+            let mut lines = String::new();
+            open_reader(&path)
+                .unwrap()
+                .unwrap()
+                .read_to_string(&mut lines)
+                .unwrap();
+            assert_eq!(lines.lines().count(), 5);
+            assert!(lines.contains("processed;3;200;https://example.com/a\n"));
+            assert!(lines.contains("failed;9;404;https://example.com/d\n"));
+
+            let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+            let compacted = Journal::load_history(path.clone()).unwrap();
+            assert_eq!(compacted.pending, vec![url("b")]);
+            assert_eq!(compacted.processing, vec![url("c")]);
+            assert_eq!(compacted.processed, vec![(url("a"), 3)]);
+            assert_eq!(compacted.failed, vec![url("d")]);
+            assert_eq!(compacted.attempts, HashMap::from([(url("c"), 2)]));
+            assert_eq!(
+                compacted.statuses,
+                HashMap::from([(url("a"), 200), (url("d"), 404)])
+            );
+            assert_eq!(compacted.failed_at, HashMap::from([(url("d"), 9)]));
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entries_flushed_periodically() {
+        let path =
+            std::env::temp_dir().join(format!("yoink-flush-ms-{}.log.gz", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let a = Url::from_str("https://example.com/a").unwrap();
+        let (mut journal, journal_task) =
+            Journal::new(path.clone(), Some(Duration::from_millis(50))).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: a.clone(),
+            at: 0,
+        });
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        assert_eq!(
+            Journal::load_history(path.clone()).unwrap().pending,
+            vec![a]
+        );
+
+        drop(journal);
+        journal_handle.await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_unfinished_compressed_member() {
+        let path =
+            std::env::temp_dir().join(format!("yoink-unfinished-{}.log.gz", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: url("a"),
+            at: 0,
+        });
+        journal.flush_now().await.unwrap();
+        journal_handle.abort();
+        assert!(journal_handle.await.unwrap_err().is_cancelled());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(b"pending;0;https://example.com/b\n")
+            .unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(&encoder.finish().unwrap()).unwrap();
+
+        let (mut journal, journal_task) = Journal::new(path.clone(), None).unwrap();
+        let journal_handle = tokio::spawn(journal_task);
+        journal.send(JournalEntry::Pending {
+            url: url("c"),
+            at: 0,
+        });
+        drop(journal);
+        journal_handle.await.unwrap();
+
+        let mut content = String::new();
+        open_reader(&path)
+            .unwrap()
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(
+            content,
+            "pending;0;https://example.com/a\npending;0;https://example.com/b\n\
+             pending;0;https://example.com/c\n"
+        );
+        assert_eq!(uncompressed_len(&path).unwrap(), content.len() as u64);
+        assert_eq!(
+            Journal::load_history(path.clone()).unwrap().pending,
+            vec![url("a"), url("b"), url("c")]
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
@@ -0,0 +1,415 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonError {
+    UnexpectedEnd,
+    UnexpectedCharacter { ch: char, index: usize },
+    InvalidNumber { index: usize },
+    InvalidEscape { index: usize },
+    TrailingCharacters { index: usize },
+    TooDeep { index: usize },
+}
+
+impl std::error::Error for JsonError {}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::UnexpectedEnd => write!(f, "unexpected end of json"),
+            JsonError::UnexpectedCharacter { ch, index } => {
+                write!(f, "unexpected character {ch:?} at {index}")
+            }
+            JsonError::InvalidNumber { index } => write!(f, "invalid number at {index}"),
+            JsonError::InvalidEscape { index } => write!(f, "invalid escape at {index}"),
+            JsonError::TrailingCharacters { index } => {
+                write!(f, "trailing characters at {index}")
+            }
+            JsonError::TooDeep { index } => {
+                write!(f, "nesting deeper than {MAX_DEPTH} levels at {index}")
+            }
+        }
+    }
+}
+
+impl JsonValue {
+    pub fn parse(input: &str) -> Result<Self, JsonError> {
+        let mut parser = Parser {
+            chars: input.chars().collect(),
+            index: 0,
+            depth: 0,
+        };
+
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.index != parser.chars.len() {
+            return Err(JsonError::TrailingCharacters {
+                index: parser.index,
+            });
+        }
+
+        Ok(value)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn select(&self, path: &str) -> Vec<&JsonValue> {
+        let mut current = vec![self];
+
+        for key in path.split('.').filter(|key| !key.is_empty()) {
+            current = current
+                .into_iter()
+                .flat_map(|value| match value {
+                    JsonValue::Array(values) => values.iter().filter_map(|v| v.get(key)).collect(),
+                    value => value.get(key).into_iter().collect::<Vec<_>>(),
+                })
+                .collect();
+        }
+
+        current
+            .into_iter()
+            .flat_map(|value| match value {
+                JsonValue::Array(values) => values.iter().collect(),
+                value => vec![value],
+            })
+            .collect()
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonValue::Null => write!(f, "null"),
+            JsonValue::Bool(b) => write!(f, "{b}"),
+            JsonValue::Number(n) if n.is_finite() => write!(f, "{n}"),
+            JsonValue::Number(_) => write!(f, "null"),
+            JsonValue::String(s) => write_string(f, s),
+            JsonValue::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            JsonValue::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+const MAX_DEPTH: usize = 128;
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Result<char, JsonError> {
+        let ch = self.peek().ok_or(JsonError::UnexpectedEnd)?;
+        self.index += 1;
+        Ok(ch)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        let index = self.index;
+        match self.next()? {
+            ch if ch == expected => Ok(()),
+            ch => Err(JsonError::UnexpectedCharacter { ch, index }),
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str) -> Result<(), JsonError> {
+        literal.chars().try_for_each(|ch| self.expect(ch))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|ch| ch.is_ascii_whitespace()) {
+            self.index += 1;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<JsonValue, JsonError> {
+        self.skip_whitespace();
+
+        match self.peek().ok_or(JsonError::UnexpectedEnd)? {
+            'n' => self.expect_literal("null").map(|_| JsonValue::Null),
+            't' => self.expect_literal("true").map(|_| JsonValue::Bool(true)),
+            'f' => self.expect_literal("false").map(|_| JsonValue::Bool(false)),
+            '"' => self.parse_string().map(JsonValue::String),
+            '[' | '{' => {
+                if self.depth >= MAX_DEPTH {
+                    return Err(JsonError::TooDeep { index: self.index });
+                }
+                self.depth += 1;
+                let value = if self.peek() == Some('[') {
+                    self.parse_array()
+                } else {
+                    self.parse_object()
+                };
+                self.depth -= 1;
+                value
+            }
+            '-' | '0'..='9' => self.parse_number(),
+            ch => Err(JsonError::UnexpectedCharacter {
+                ch,
+                index: self.index,
+            }),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<JsonValue, JsonError> {
+        let start = self.index;
+        while self
+            .peek()
+            .is_some_and(|ch| matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+        {
+            self.index += 1;
+        }
+
+        let number: String = self.chars[start..self.index].iter().collect();
+        number
+            .parse()
+            .map(JsonValue::Number)
+            .map_err(|_| JsonError::InvalidNumber { index: start })
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+
+        let mut out = String::new();
+        loop {
+            let index = self.index;
+            match self.next()? {
+                '"' => return Ok(out),
+                '\\' => match self.next()? {
+                    '"' => out.push('"'),
+                    '\\' => out.push('\\'),
+                    '/' => out.push('/'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'u' => {
+                        let high = self.parse_hex4(index)?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            self.expect('\\')?;
+                            self.expect('u')?;
+                            let low = self.parse_hex4(index)?;
+                            0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                        } else {
+                            high
+                        };
+                        out.push(char::from_u32(code).ok_or(JsonError::InvalidEscape { index })?);
+                    }
+                    _ => return Err(JsonError::InvalidEscape { index }),
+                },
+                ch => out.push(ch),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self, index: usize) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()?
+                .to_digit(16)
+                .ok_or(JsonError::InvalidEscape { index })?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn parse_array(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('[')?;
+
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.index += 1;
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            let index = self.index;
+            match self.next()? {
+                ',' => continue,
+                ']' => return Ok(JsonValue::Array(values)),
+                ch => return Err(JsonError::UnexpectedCharacter { ch, index }),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<JsonValue, JsonError> {
+        self.expect('{')?;
+
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.index += 1;
+            return Ok(JsonValue::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let name = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((name, self.parse_value()?));
+            self.skip_whitespace();
+
+            let index = self.index;
+            match self.next()? {
+                ',' => continue,
+                '}' => return Ok(JsonValue::Object(fields)),
+                ch => return Err(JsonError::UnexpectedCharacter { ch, index }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_serialize() {
+        let input = r#" {"a": [1, 2.5, -3e2], "b": {"c": null, "d": true}, "e": "x\"yé\n"} "#;
+        let value = JsonValue::parse(input).unwrap();
+
+        assert_eq!(value.get("a").unwrap().as_array().unwrap().len(), 3);
+        assert_eq!(value.get("b").unwrap().get("c"), Some(&JsonValue::Null));
+        assert_eq!(value.get("e").unwrap().as_str(), Some("x\"yé\n"));
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,2.5,-300],"b":{"c":null,"d":true},"e":"x\"yé\n"}"#
+        );
+        assert_eq!(JsonValue::parse(&value.to_string()).unwrap(), value);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(JsonValue::parse(""), Err(JsonError::UnexpectedEnd));
+        assert_eq!(JsonValue::parse("[1,"), Err(JsonError::UnexpectedEnd));
+        assert_eq!(
+            JsonValue::parse("{} x"),
+            Err(JsonError::TrailingCharacters { index: 3 })
+        );
+        assert!(matches!(
+            JsonValue::parse("{\"a\" 1}"),
+            Err(JsonError::UnexpectedCharacter { ch: '1', .. })
+        ));
+
+        let nested = format!("{}{}", "[".repeat(MAX_DEPTH), "]".repeat(MAX_DEPTH));
+        assert!(JsonValue::parse(&nested).is_ok());
+        assert_eq!(
+            JsonValue::parse(&"[".repeat(200_000)),
+            Err(JsonError::TooDeep { index: MAX_DEPTH })
+        );
+        assert_eq!(
+            JsonValue::parse(&"{\"a\":".repeat(200_000)),
+            Err(JsonError::TooDeep {
+                index: 5 * MAX_DEPTH
+            })
+        );
+    }
+
+    #[test]
+    fn test_select_dotted_path() {
+        let value = JsonValue::parse(
+            r#"{"next": "/page/2", "_links": {"next": {"href": "/api?page=2"}},
+                "items": [{"url": "/a"}, {"url": "/b"}, {"name": "c"}], "tags": ["/x", "/y"]}"#,
+        )
+        .unwrap();
+
+        let strings = |path| {
+            value
+                .select(path)
+                .into_iter()
+                .filter_map(|value| value.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(strings("next"), vec!["/page/2"]);
+        assert_eq!(strings("_links.next.href"), vec!["/api?page=2"]);
+        assert_eq!(strings("items.url"), vec!["/a", "/b"]);
+        assert_eq!(strings("tags"), vec!["/x", "/y"]);
+        assert!(strings("missing.path").is_empty());
+    }
+}
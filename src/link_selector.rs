@@ -0,0 +1,81 @@
+use std::str::FromStr;
+
+use scraper::{Html, Selector};
+
+pub const DEFAULT_LINK_SELECTORS: [&str; 4] = ["a@href", "area@href", "iframe@src", "frame@src"];
+
+#[derive(Debug, Clone)]
+pub struct LinkSelector {
+    selector: Selector,
+    attribute: String,
+}
+
+impl FromStr for LinkSelector {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (selector, attribute) = value.rsplit_once('@').unwrap_or((value, "href"));
+        if attribute.is_empty() {
+            return Err(format!("empty attribute in {value:?}"));
+        }
+        let selector = Selector::parse(selector).map_err(|err| err.to_string())?;
+
+        Ok(LinkSelector {
+            selector,
+            attribute: attribute.to_owned(),
+        })
+    }
+}
+
+pub fn extract_links(document: &Html, link_selectors: &[LinkSelector]) -> Vec<String> {
+    link_selectors
+        .iter()
+        .flat_map(|link_selector| {
+            document
+                .select(&link_selector.selector)
+                .filter_map(|element| element.attr(&link_selector.attribute).map(String::from))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_selectors() -> Vec<LinkSelector> {
+        DEFAULT_LINK_SELECTORS
+            .iter()
+            .map(|value| LinkSelector::from_str(value).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_selectors_cover_frames_and_areas() {
+        let document = Html::parse_document(
+            r#"<a href="/docs">Docs</a><iframe src="/embed"></iframe>
+            <map><area href="/region" shape="rect"></map><iframe></iframe>"#,
+        );
+
+        assert_eq!(
+            extract_links(&document, &default_selectors()),
+            vec!["/docs", "/region", "/embed"]
+        );
+    }
+
+    #[test]
+    fn test_parse_link_selector() {
+        let document = Html::parse_document(
+            r#"<head><link rel="next" href="/page/2"><link rel="icon" href="/favicon.ico"></head>
+            <form action="/search"></form><a href="/home">Home</a>"#,
+        );
+        let selectors =
+            ["link[rel=next]", "form@action"].map(|value| LinkSelector::from_str(value).unwrap());
+
+        assert_eq!(
+            extract_links(&document, &selectors),
+            vec!["/page/2", "/search"]
+        );
+        assert!(LinkSelector::from_str("a@").is_err());
+        assert!(LinkSelector::from_str("[[[@href").is_err());
+    }
+}
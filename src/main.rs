@@ -2,46 +2,274 @@ use std::{
     fs::create_dir_all,
     path::{Path, PathBuf},
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
-use clap::Parser;
-use reqwest::Client;
-use scraper::{Html, Selector};
+use clap::{Parser, Subcommand};
+use regex::Regex;
+use reqwest::{Client, dns::Resolve};
 use tokio::{
-    fs::File,
-    io::AsyncWriteExt,
-    sync::{Mutex, Semaphore},
+    select, signal,
+    sync::{Mutex, Semaphore, watch},
     task::JoinSet,
-    time::interval,
+    time::{interval, sleep, timeout},
 };
-use url::Url;
+use url::{FilenameScheme, FragmentPolicy, Seed, Url};
 
 use crate::{
-    encoding::url_encode,
-    journal::{Journal, JournalEntry},
+    body_status::BodyStatusSelector,
+    checkpoint::Checkpoint,
+    client::HttpVersion,
+    cookies::load_cookie_jar,
+    crawl::{CrawlContext, crawl_url},
+    diff::CrawlDiff,
+    dns::{DohResolver, LimitedResolver, SystemResolver},
+    dropped_links::DroppedLinks,
+    duplicates::{ContentDedupe, DuplicateContent, TitleDedupe},
+    filter::{UrlFilter, UrlPattern},
+    frontier::{read_url_list, write_url_list},
+    host_budget::HostBudget,
+    host_interval::HostInterval,
+    host_sample::HostSample,
+    inventory::Discoveries,
+    ip_limit::IpLimiter,
+    journal::{Journal, JournalEntry, JournalHistory},
+    link_selector::{DEFAULT_LINK_SELECTORS, LinkSelector},
+    meta::CrawlMeta,
+    metrics::{ByteCounter, CrawlMetrics},
+    output::{OnExisting, OutputFormat, OutputSink, QueryInFilename, SaveOptions},
+    progress::Progress,
     queue::Queue,
+    record::RecordSchema,
+    redirect::{OffsiteRedirect, RedirectOptions, build_policy},
+    refresh::now_secs,
+    retry::{RetryPolicy, UserAgentFallback},
+    robots::Robots,
+    scope::{Scope, ScopeRule},
+    sitemap::fetch_sitemap_urls,
+    stats::JournalStats,
+    timeouts::RequestTimeouts,
+    trace::{TraceEvent, Tracer},
+    watchdog::Watchdog,
 };
 
+mod body_status;
+mod checkpoint;
+mod client;
+mod cookies;
+mod crawl;
+mod diff;
+mod dns;
+mod dropped_links;
+mod duplicates;
 mod encoding;
+mod filter;
+mod frontier;
+mod host_budget;
+mod host_interval;
+mod host_sample;
+mod inventory;
+mod ip_limit;
 mod journal;
+mod json;
+mod link_selector;
+mod meta;
+mod metrics;
+mod output;
+mod progress;
 mod queue;
+mod record;
+mod redirect;
+mod refresh;
+mod retry;
+mod robots;
+mod scope;
+mod sitemap;
+mod stats;
+#[cfg(test)]
+mod test_server;
+mod timeouts;
+mod trace;
 mod url;
+mod watchdog;
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    Diff {
+        #[arg(long)]
+        old: PathBuf,
+        #[arg(long)]
+        new: PathBuf,
+    },
+    DumpJournalStats {
+        #[arg(long)]
+        journal: PathBuf,
+    },
+}
 
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
 struct Args {
-    #[arg(long)]
-    url: Url,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(long, required = true)]
+    url: Option<Seed>,
     #[arg(long, default_value_t = 100)]
     concurrency_limit: usize,
+    #[arg(long)]
+    max_concurrent_per_ip: Option<usize>,
+    #[arg(long)]
+    max_host_time_secs: Option<u64>,
+    #[arg(long)]
+    follow_only_first_n_per_host: Option<usize>,
     #[arg(long, default_value_t = 1000)]
     request_timeout_ms: u64,
+    #[arg(long)]
+    timeout_large_ms: Option<u64>,
     #[arg(long, default_value_t = 100)]
     min_interval_ms: u64,
+    #[arg(long)]
+    per_host_interval_ms: Option<u64>,
+    #[arg(long, default_value_t = 0)]
+    retries: u32,
+    #[arg(long, default_value_t = 0)]
+    body_read_retries: u32,
     #[arg(long, default_value = "Mozilla/5.0")]
     user_agent: String,
+    #[arg(long)]
+    fallback_user_agent: Vec<String>,
+    #[arg(long, value_enum)]
+    http_version: Option<HttpVersion>,
+    #[arg(long)]
+    dns_over_https: Option<String>,
+    #[arg(long)]
+    max_dns_concurrency: Option<usize>,
+    #[arg(long)]
+    cookies_json: Option<PathBuf>,
     #[arg(long, default_value = "scraper_output")]
     output_directory: PathBuf,
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "html")]
+    output: Vec<OutputFormat>,
+    #[arg(long)]
+    record_schema: Option<PathBuf>,
+    #[arg(long)]
+    flush_html_sync: bool,
+    #[arg(long)]
+    compress_journal: bool,
+    #[arg(long)]
+    checkpoint_interval_secs: Option<u64>,
+    #[arg(long)]
+    compact_journal: bool,
+    #[arg(long, default_value_t = 500)]
+    journal_flush_ms: u64,
+    #[arg(long, value_delimiter = ',')]
+    merge_journals: Vec<PathBuf>,
+    #[arg(long, value_delimiter = ',', default_value = "text/html")]
+    content_types: Vec<String>,
+    #[arg(long)]
+    mirror_layout: bool,
+    #[arg(long, value_enum, default_value_t = QueryInFilename::On)]
+    query_in_filename: QueryInFilename,
+    #[arg(long, value_enum, default_value_t = OnExisting::Overwrite)]
+    on_existing: OnExisting,
+    #[arg(long)]
+    force: bool,
+    #[arg(long, value_enum, default_value_t = Scope::Host)]
+    scope: Scope,
+    #[arg(long, value_delimiter = ',')]
+    extra_hosts: Vec<String>,
+    #[arg(long)]
+    scope_rule: Vec<ScopeRule>,
+    #[arg(long)]
+    respect_robots: bool,
+    #[arg(long, requires = "respect_robots")]
+    respect_crawl_delay: bool,
+    #[arg(long, value_enum, default_value_t = FragmentPolicy::Strip)]
+    fragments: FragmentPolicy,
+    #[arg(long)]
+    include: Vec<UrlPattern>,
+    #[arg(long)]
+    exclude: Vec<UrlPattern>,
+    #[arg(long)]
+    include_pattern: Vec<Regex>,
+    #[arg(long)]
+    exclude_pattern: Vec<Regex>,
+    #[arg(long)]
+    strip_session_params: bool,
+    #[arg(long)]
+    normalize_path_case: bool,
+    #[arg(long)]
+    head_start_urls: Option<PathBuf>,
+    #[arg(long)]
+    use_sitemap: bool,
+    #[arg(long)]
+    import_frontier: Option<PathBuf>,
+    #[arg(long)]
+    export_frontier: Option<PathBuf>,
+    #[arg(long)]
+    export_processing: bool,
+    #[arg(long)]
+    json_link_path: Vec<String>,
+    #[arg(long)]
+    priority_pattern: Vec<Regex>,
+    #[arg(long)]
+    enqueue_batch_size: Option<usize>,
+    #[arg(long)]
+    max_dom_nodes: Option<usize>,
+    #[arg(long)]
+    login_url_pattern: Option<Regex>,
+    #[arg(long)]
+    body_status_selector: Option<BodyStatusSelector>,
+    #[arg(long, value_enum, default_value_t = OffsiteRedirect::Skip)]
+    offsite_redirect: OffsiteRedirect,
+    #[arg(long, default_value_t = 5)]
+    max_query_redirects: usize,
+    #[arg(long, default_value_t = 10)]
+    max_redirects: usize,
+    #[arg(long)]
+    refresh_after_secs: Option<u64>,
+    #[arg(long)]
+    max_empty_polls: Option<u32>,
+    #[arg(long, default_value_t = 30000)]
+    idle_poll_timeout_ms: u64,
+    #[arg(long, default_value_t = 30)]
+    shutdown_timeout_secs: u64,
+    #[arg(long)]
+    trace_url: Option<Url>,
+    #[arg(long)]
+    print_extracted_links: bool,
+    #[arg(long)]
+    canonical_first: bool,
+    #[arg(long)]
+    ignore_base_href: bool,
+    #[arg(long, default_values = DEFAULT_LINK_SELECTORS)]
+    link_selector: Vec<LinkSelector>,
+    #[arg(long)]
+    follow_srcset: bool,
+    #[arg(long)]
+    max_depth: Option<u32>,
+    #[arg(long)]
+    max_pages: Option<usize>,
+    #[arg(long)]
+    min_content_length: Option<usize>,
+    #[arg(long)]
+    max_hosts: Option<usize>,
+    #[arg(long)]
+    probe_only: bool,
+    #[arg(long)]
+    allow_length_mismatch: bool,
+    #[arg(long)]
+    show_eta: bool,
+    #[arg(long)]
+    report_duplicates: bool,
+    #[arg(long)]
+    dedupe_content: bool,
+    #[arg(long)]
+    dedupe_titles: bool,
+    #[arg(long)]
+    report_dropped_links: bool,
+    #[arg(long)]
+    metrics_file: Option<PathBuf>,
     #[arg(long)]
     verbose: bool,
 }
@@ -50,165 +278,567 @@ struct Args {
 async fn main() {
     let args = Args::parse();
 
-    let html_directory = args.output_directory.join("html");
-    let journal_path = args.output_directory.join("journal.log");
-    create_dir_all(&html_directory).expect("Failed to create output directory");
-    let html_directory = Arc::new(html_directory);
+    match args.command {
+        Some(Command::Diff { old, new }) => {
+            CrawlDiff::new(&load_history(old), &load_history(new)).print();
+            return;
+        }
+        Some(Command::DumpJournalStats { journal }) => {
+            JournalStats::new(&load_history(journal)).print();
+            return;
+        }
+        None => {}
+    }
+    let seed = args.url.expect("--url is required when crawling");
+
+    let journal_path = args.output_directory.join(if args.compress_journal {
+        "journal.log.gz"
+    } else {
+        "journal.log"
+    });
+    create_dir_all(&args.output_directory).expect("Failed to create output directory");
+    let existing_meta = CrawlMeta::read(&args.output_directory);
+    if let Err(err) = CrawlMeta::check_seed(existing_meta.as_ref(), &seed.url, args.force) {
+        eprintln!("Refusing to crawl: {err}");
+        std::process::exit(1);
+    }
+    let meta = match existing_meta {
+        Some(meta) if meta.seed == seed.url => meta,
+        _ => CrawlMeta {
+            seed: seed.url.clone(),
+            started_at: now_secs(),
+        },
+    };
+    meta.write(&args.output_directory)
+        .expect("Failed to write crawl metadata");
+    let save_options = SaveOptions {
+        sync: args.flush_html_sync,
+        on_existing: args.on_existing,
+        query_in_filename: args.query_in_filename,
+        filename_scheme: if args.mirror_layout {
+            FilenameScheme::Mirror
+        } else {
+            FilenameScheme::Encoded
+        },
+    };
+    let mut sinks: Vec<OutputSink> = args
+        .output
+        .iter()
+        .map(|format| {
+            OutputSink::new(*format, &args.output_directory, &save_options)
+                .expect("Failed to create output sink")
+        })
+        .collect();
+    if let Some(path) = &args.record_schema {
+        let schema = RecordSchema::load(path).expect("Failed to load record schema");
+        sinks.push(
+            OutputSink::records(schema, &args.output_directory)
+                .expect("Failed to create record output sink"),
+        );
+    }
+    let sinks = Arc::new(sinks);
+    let extra_hosts: Vec<String> = args
+        .extra_hosts
+        .iter()
+        .map(|host| host.to_ascii_lowercase())
+        .collect();
 
-    let client = Client::builder()
-        .user_agent(args.user_agent)
+    let mut client_builder = Client::builder()
+        .user_agent(&args.user_agent)
         .timeout(Duration::from_millis(args.request_timeout_ms))
-        .build()
-        .expect("Failed to build client");
-    let base_url = args.url;
-    let link_selector = Selector::parse("a").expect("Failed to parse anchor tag selector");
+        .redirect(build_policy(RedirectOptions {
+            login_url_pattern: args.login_url_pattern.clone(),
+            offsite_redirect: args.offsite_redirect,
+            seed: seed.url.clone(),
+            scope: args.scope,
+            extra_hosts: extra_hosts.clone(),
+            scope_rules: args.scope_rule.clone(),
+            max_query_redirects: args.max_query_redirects,
+            max_redirects: args.max_redirects,
+        }));
+    if let Some(http_version) = args.http_version {
+        client_builder = http_version.configure(client_builder);
+    }
+    if let Some(path) = &args.cookies_json {
+        let jar = load_cookie_jar(path).expect("Failed to load cookies");
+        client_builder = client_builder.cookie_provider(Arc::new(jar));
+    }
+    let resolver: Arc<dyn Resolve> = match args.dns_over_https {
+        Some(endpoint) => {
+            let resolver = Arc::new(DohResolver::new(endpoint));
+            client_builder = client_builder.dns_resolver(resolver.clone());
+            resolver
+        }
+        None => Arc::new(SystemResolver),
+    };
+    let resolver: Arc<dyn Resolve> = match args.max_dns_concurrency {
+        Some(max_concurrent) => {
+            let resolver = Arc::new(LimitedResolver::new(resolver, max_concurrent));
+            client_builder = client_builder.dns_resolver(resolver.clone());
+            resolver
+        }
+        None => resolver,
+    };
+    let ip_limiter = args
+        .max_concurrent_per_ip
+        .map(|max_concurrent| Arc::new(IpLimiter::new(resolver, max_concurrent)));
+    let client = client_builder.build().expect("Failed to build client");
+    let base_url = seed.url.normalize();
+    let robots = if args.respect_robots {
+        Some(Robots::fetch(&client, &base_url, &args.user_agent).await)
+    } else {
+        None
+    };
+    let tracer = Tracer::new(args.trace_url);
 
-    let journal_history = Journal::load_history(journal_path.clone());
-    let queue = Arc::new(Mutex::new(Queue::new_with_initial(
+    let checkpoint_interval = args.checkpoint_interval_secs.map(Duration::from_secs);
+    // Checkpoints come from the queue, which keeps no statuses, so compaction replays the journal
+    let checkpoint = checkpoint_interval
+        .filter(|_| !args.compact_journal)
+        .and_then(|_| Checkpoint::load(&args.output_directory));
+    let journal_history = match checkpoint {
+        Some(checkpoint) => Journal::load_history_from(
+            journal_path.clone(),
+            checkpoint.history,
+            checkpoint.journal_offset,
+        ),
+        None => Journal::load_history(journal_path.clone()),
+    };
+    let mut journal_history = match journal_history {
+        Ok(journal_history) => journal_history,
+        Err(err) => {
+            eprintln!("Refusing to crawl: {err}");
+            std::process::exit(1);
+        }
+    };
+    if args.compact_journal {
+        let compacted = Checkpoint::remove(&args.output_directory)
+            .and_then(|()| Journal::compact(&journal_path, &journal_history));
+        match compacted {
+            Ok(entries) => eprintln!("Compacted journal to {entries} entries"),
+            Err(err) => eprintln!("Failed to compact journal: {err}"),
+        }
+    }
+    let mut merged_entries = Vec::new();
+    for path in &args.merge_journals {
+        merged_entries.extend(journal_history.merge(load_history(path.clone()), now_secs()));
+    }
+    let attempts = std::mem::take(&mut journal_history.attempts);
+    let mut queue = Queue::new_with_initial(
         &base_url,
         journal_history.pending,
         journal_history.processing,
         journal_history.processed,
         journal_history.failed,
-    )));
-    let (journal, journal_task) = Journal::new(journal_path);
+    );
+    queue.restore_attempts(attempts);
+    if args.normalize_path_case {
+        queue.fold_path_case();
+    }
+    if let Some(max_depth) = args.max_depth {
+        queue.set_max_depth(max_depth);
+    }
+    if let Some(max_hosts) = args.max_hosts {
+        queue.set_max_hosts(max_hosts);
+    }
+    let interrupted = queue.requeue_processing_as_pending();
+    if !interrupted.is_empty() {
+        eprintln!(
+            "Requeued {} urls left processing by a previous run",
+            interrupted.len()
+        );
+    }
+    let queue = Arc::new(Mutex::new(queue));
+    let journal_flush_interval =
+        (args.journal_flush_ms > 0).then(|| Duration::from_millis(args.journal_flush_ms));
+    let (mut journal, journal_task) = match Journal::new(journal_path, journal_flush_interval) {
+        Ok(journal) => journal,
+        Err(err) => {
+            eprintln!("Refusing to crawl: {err}");
+            std::process::exit(1);
+        }
+    };
     let journal_handle = tokio::spawn(journal_task);
+    for url in interrupted {
+        journal.send(JournalEntry::Pending {
+            url,
+            at: now_secs(),
+        });
+    }
+    for entry in merged_entries {
+        journal.send(entry);
+    }
+
+    let discoveries = args
+        .output
+        .contains(&OutputFormat::Inventory)
+        .then(Discoveries::default);
+    if let Some(discoveries) = &discoveries {
+        discoveries.record(&base_url, None, now_secs());
+    }
+
+    for path in [&args.head_start_urls, &args.import_frontier]
+        .into_iter()
+        .flatten()
+    {
+        let urls = read_url_list(
+            path,
+            &base_url,
+            args.scope,
+            &extra_hosts,
+            &args.scope_rule,
+            args.fragments,
+        )
+        .expect("Failed to read url list");
+        let mut queue = queue.lock().await;
+        for url in urls {
+            let url = url.normalize();
+            if let Some(discoveries) = &discoveries {
+                discoveries.record(&url, None, now_secs());
+            }
+            if queue.add_pending(&url) {
+                journal.send(JournalEntry::Pending {
+                    url,
+                    at: now_secs(),
+                });
+            }
+        }
+    }
+
+    if args.use_sitemap {
+        let urls = fetch_sitemap_urls(&client, &base_url).await;
+        let mut queue = queue.lock().await;
+        let mut added = 0;
+        for url in urls {
+            if let Some(discoveries) = &discoveries {
+                discoveries.record(&url, None, now_secs());
+            }
+            if queue.add_pending(&url) {
+                added += 1;
+                journal.send(JournalEntry::Pending {
+                    url,
+                    at: now_secs(),
+                });
+            }
+        }
+        eprintln!("Seeded {added} urls from the sitemap");
+    }
 
     let semaphore = Arc::new(Semaphore::new(args.concurrency_limit));
     let mut join_set = JoinSet::new();
 
-    let delay = Duration::from_millis(args.min_interval_ms);
-    let interval = Arc::new(Mutex::new(interval(delay)));
+    // The interval is shared by every task, so Crawl-delay from the seed's robots.txt applies
+    // to the whole crawl rather than per host
+    let mut delay = Duration::from_millis(args.min_interval_ms);
+    if args.respect_crawl_delay
+        && let Some(robots) = &robots
+    {
+        delay = robots.effective_interval(delay);
+    }
+    // tokio rejects a zero period, which is natural when only per-host spacing is wanted
+    let interval = Arc::new(Mutex::new(interval(delay.max(Duration::from_millis(1)))));
+    let duplicates = args.report_duplicates.then(DuplicateContent::default);
+    let dropped_links = args
+        .report_dropped_links
+        .then(|| DroppedLinks::new(args.verbose));
+    let downloaded = args.metrics_file.is_some().then(ByteCounter::default);
+    let user_agent_fallback = (!args.fallback_user_agent.is_empty())
+        .then(|| UserAgentFallback::new(args.fallback_user_agent));
+    let ctx = CrawlContext {
+        queue: queue.clone(),
+        journal: journal.clone(),
+        client,
+        http_version: args.http_version,
+        credentials: seed.credentials,
+        base_url,
+        scope: args.scope,
+        extra_hosts: Arc::new(extra_hosts),
+        scope_rules: Arc::new(args.scope_rule),
+        link_selectors: Arc::new(args.link_selector),
+        json_link_paths: Arc::new(args.json_link_path),
+        sinks,
+        interval,
+        priority_patterns: Arc::new(args.priority_pattern),
+        login_url_pattern: Arc::new(args.login_url_pattern),
+        robots: Arc::new(robots),
+        offsite_redirect: args.offsite_redirect,
+        tracer: tracer.clone(),
+        duplicates: duplicates.clone(),
+        dropped_links: dropped_links.clone(),
+        content_dedupe: args.dedupe_content.then(ContentDedupe::default),
+        title_dedupe: args.dedupe_titles.then(TitleDedupe::default),
+        downloaded: downloaded.clone(),
+        probe_only: args.probe_only,
+        allow_length_mismatch: args.allow_length_mismatch,
+        fragments: args.fragments,
+        ip_limiter,
+        discoveries,
+        body_status_selector: Arc::new(args.body_status_selector),
+        enqueue_batch_size: args.enqueue_batch_size,
+        strip_session_params: args.strip_session_params,
+        print_extracted_links: args.print_extracted_links,
+        max_dom_nodes: args.max_dom_nodes,
+        canonical_first: args.canonical_first,
+        ignore_base_href: args.ignore_base_href,
+        follow_srcset: args.follow_srcset,
+        min_content_length: args.min_content_length,
+        request_timeouts: args.timeout_large_ms.map(|large| RequestTimeouts {
+            default: Duration::from_millis(args.request_timeout_ms),
+            large: Duration::from_millis(large),
+        }),
+        content_types: Arc::new(args.content_types),
+        host_interval: args
+            .per_host_interval_ms
+            .map(|interval_ms| HostInterval::new(Duration::from_millis(interval_ms))),
+        host_sample: args.follow_only_first_n_per_host.map(HostSample::new),
+        user_agent_fallback: user_agent_fallback.clone(),
+        url_filter: Arc::new(UrlFilter {
+            include: args.include,
+            exclude: args.exclude,
+            include_patterns: args.include_pattern,
+            exclude_patterns: args.exclude_pattern,
+        }),
+        host_budget: args
+            .max_host_time_secs
+            .map(|secs| HostBudget::new(Duration::from_secs(secs))),
+        retry_policy: RetryPolicy {
+            request_retries: args.retries,
+            body_read_retries: args.body_read_retries,
+        },
+    };
+
+    let refresh_after = args.refresh_after_secs.map(Duration::from_secs);
+    let idle_poll_timeout = Duration::from_millis(args.idle_poll_timeout_ms);
+    let mut watchdog = Watchdog::new(args.max_empty_polls);
+    let finished_at_start = queue.lock().await.total_finished();
+    let mut progress = Progress::new(finished_at_start, Instant::now());
+
+    let mut last_checkpoint = Instant::now();
+    let mut last_metrics = Instant::now();
+
+    let (shutdown_tx, mut shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("Interrupted, waiting for in-flight requests (press Ctrl-C again to exit now)");
+        let _ = shutdown_tx.send(true);
+
+        if signal::ctrl_c().await.is_ok() {
+            eprintln!("Interrupted again, exiting without flushing the journal");
+            std::process::exit(130);
+        }
+    });
 
     loop {
+        if *shutdown.borrow() {
+            eprintln!(
+                "Stopping with {} urls pending and {} in flight, rerun with the same output directory to resume",
+                queue.lock().await.pending().count(),
+                join_set.len()
+            );
+            break;
+        }
+
+        if let Some(checkpoint_interval) = checkpoint_interval
+            && last_checkpoint.elapsed() >= checkpoint_interval
+        {
+            let queue = queue.lock().await;
+            if let Some(journal_offset) = journal.flush_now().await {
+                let checkpoint = Checkpoint {
+                    history: queue.snapshot(),
+                    journal_offset,
+                };
+                if let Err(err) = checkpoint.write(&args.output_directory) {
+                    eprintln!("Failed to write checkpoint: {err}");
+                }
+            }
+            last_checkpoint = Instant::now();
+        }
+
+        if let Some(path) = &args.metrics_file
+            && last_metrics.elapsed() >= metrics::WRITE_INTERVAL
+        {
+            let queue = queue.lock().await;
+            progress.update(queue.total_finished(), Instant::now());
+            write_metrics(path, &queue, &downloaded, &progress);
+            last_metrics = Instant::now();
+        }
+
         let next = {
             let mut queue = queue.lock().await;
+            if let Some(max_pages) = args.max_pages
+                && queue.total_finished().saturating_sub(finished_at_start) >= max_pages
+            {
+                println!(
+                    "Reached --max-pages {max_pages}, stopping with {} urls still pending",
+                    queue.pending().count()
+                );
+                break;
+            }
             queue.next()
         };
 
-        if let Some(url) = next {
+        if let Some((url, depth)) = next {
             let permit = semaphore
                 .clone()
                 .acquire_owned()
                 .await
                 .expect("Failed to acquire permit from semaphore");
-            let queue = queue.clone();
-            let mut journal = journal.clone();
-            let client = client.clone();
-            let base_url = base_url.clone();
-            let link_selector = link_selector.clone();
-            let html_directory = html_directory.clone();
-
-            let interval = interval.clone();
 
-            if args.verbose {
+            if args.verbose || args.show_eta {
                 let queue = queue.lock().await;
-                queue.print_summary();
+                if args.verbose {
+                    queue.print_summary();
+                }
+                if args.show_eta {
+                    progress.update(queue.total_finished(), Instant::now());
+                    println!("{}", progress.status(queue.remaining()));
+                }
             }
 
+            tracer.trace(&url, TraceEvent::Dispatched { depth });
             journal.send(JournalEntry::Processing {
                 url: url.to_owned(),
+                at: now_secs(),
             });
 
+            let ctx = ctx.clone();
             join_set.spawn(async move {
                 let _permit = permit;
-
-                {
-                    let mut interval = interval.lock().await;
-                    interval.tick().await;
-                }
-
-                let resp = match client.get(url.to_string()).send().await {
-                    Ok(r) => r,
-                    Err(err) => {
-                        let mut queue = queue.lock().await;
-                        queue.mark_as_failed(&url);
-                        journal.send(JournalEntry::Failed {
-                            url: url.to_owned(),
-                        });
-                        eprintln!("Request failed for {url}: {err}");
-                        return;
-                    }
-                };
-                let mut queue = queue.lock().await;
-
-                let body = match resp.text().await {
-                    Ok(b) => b,
-                    Err(err) => {
-                        queue.mark_as_failed(&url);
-                        journal.send(JournalEntry::Failed {
-                            url: url.to_owned(),
-                        });
-                        eprintln!("Failed to read body for {url}: {err}");
-                        return;
-                    }
+                crawl_url(ctx, url).await;
+            });
+        } else {
+            if join_set.is_empty() {
+                let Some(refresh_after) = refresh_after else {
+                    break;
                 };
 
-                let urls = extract_links_from_body(&body, &link_selector);
-
-                for url_or_path in urls {
-                    if let Ok(url) = Url::new_with_base(&base_url, &url_or_path) {
-                        queue.add_pending(&url);
+                let mut queue = queue.lock().await;
+                let stale = queue.refresh_stale(now_secs(), refresh_after);
+                if !stale.is_empty() {
+                    for url in stale {
                         journal.send(JournalEntry::Pending {
-                            url: url.to_owned(),
+                            url,
+                            at: now_secs(),
                         });
                     }
+                    continue;
                 }
 
-                if let Err(err) = save_html(&html_directory, &url, &body).await {
-                    queue.mark_as_failed(&url);
-                    journal.send(JournalEntry::Failed {
-                        url: url.to_owned(),
-                    });
-                    println!("Failed to save html for {url}: {err}");
-                    return;
+                let Some(wait) = queue.next_refresh_in(now_secs(), refresh_after) else {
+                    break;
+                };
+                drop(queue);
+                select! {
+                    _ = sleep(wait.max(Duration::from_secs(1))) => {}
+                    _ = shutdown.changed() => {}
                 }
+                continue;
+            }
 
-                queue.mark_as_processed(&url);
-                journal.send(JournalEntry::Processed {
-                    url: url.to_owned(),
-                });
-            });
-        } else {
-            if join_set.is_empty() {
+            let progressed = timeout(idle_poll_timeout, join_set.join_next())
+                .await
+                .is_ok();
+            if watchdog.record(progressed) {
+                eprintln!(
+                    "No crawl progress after {} polls of {}ms with {} tasks in flight, giving up",
+                    watchdog.empty_polls(),
+                    idle_poll_timeout.as_millis(),
+                    join_set.len()
+                );
+                queue.lock().await.print_summary();
+                join_set.shutdown().await;
                 break;
             }
+        }
+    }
 
-            join_set.join_next().await;
+    if *shutdown.borrow() {
+        let shutdown_timeout = Duration::from_secs(args.shutdown_timeout_secs);
+        if timeout(shutdown_timeout, join_all(&mut join_set))
+            .await
+            .is_err()
+        {
+            eprintln!(
+                "Aborting {} requests still running after {}s, they will be retried on resume",
+                join_set.len(),
+                shutdown_timeout.as_secs()
+            );
+            join_set.shutdown().await;
         }
+    } else {
+        join_all(&mut join_set).await;
     }
 
-    while let Some(res) = join_set.join_next().await {
-        if let Err(err) = res {
-            eprintln!("Crawl task failed: {err:?}");
+    if let Some(path) = &args.metrics_file {
+        let queue = queue.lock().await;
+        progress.update(queue.total_finished(), Instant::now());
+        write_metrics(path, &queue, &downloaded, &progress);
+    }
+    if let Some(duplicates) = &duplicates {
+        duplicates.print_report();
+    }
+    if let Some(dropped_links) = &dropped_links {
+        dropped_links.print_report();
+    }
+    if let Some(user_agent_fallback) = &user_agent_fallback {
+        user_agent_fallback.print_report();
+    }
+
+    if let Some(path) = &args.export_frontier {
+        let queue = queue.lock().await;
+        let result = if args.export_processing {
+            write_url_list(path, queue.pending().chain(queue.processing()))
+        } else {
+            write_url_list(path, queue.pending())
+        };
+
+        if let Err(err) = result {
+            eprintln!("Failed to export frontier to {}: {err}", path.display());
         }
     }
 
+    drop(ctx);
     drop(journal);
     if let Err(err) = journal_handle.await {
         eprintln!("Jornal task failed: {err}");
     }
 }
 
-fn extract_links_from_body(body: &str, link_selector: &Selector) -> Vec<String> {
-    let document = Html::parse_document(body);
-
-    document
-        .select(link_selector)
-        .filter_map(|link| link.attr("href").map(String::from))
-        .collect()
+fn load_history(path: PathBuf) -> JournalHistory {
+    match Journal::load_history(path) {
+        Ok(history) => history,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    }
 }
 
-async fn save_html(html_directory: &Path, url: &Url, html: &str) -> Result<(), String> {
-    let encoded_url = url_encode(&url.to_string());
-    let file_path = html_directory.join(format!("{encoded_url}.html"));
-
-    let mut file = File::create(file_path)
-        .await
-        .map_err(|err| err.to_string())?;
-    file.write_all(html.as_bytes())
-        .await
-        .map_err(|err| err.to_string())?;
+async fn join_all(join_set: &mut JoinSet<()>) {
+    while let Some(res) = join_set.join_next().await {
+        if let Err(err) = res {
+            eprintln!("Crawl task failed: {err:?}");
+        }
+    }
+}
 
-    Ok(())
+fn write_metrics(
+    path: &Path,
+    queue: &Queue,
+    downloaded: &Option<ByteCounter>,
+    progress: &Progress,
+) {
+    let metrics = CrawlMetrics {
+        processed: queue.processed_count(),
+        failed: queue.failed_count(),
+        pending: queue.pending().count(),
+        processing: queue.processing().count(),
+        bytes: downloaded.as_ref().map_or(0, ByteCounter::total),
+        rate: progress.rate(),
+    };
+    if let Err(err) = metrics.write(path) {
+        eprintln!("Failed to write metrics to {}: {err}", path.display());
+    }
 }
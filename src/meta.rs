@@ -0,0 +1,103 @@
+use std::{path::Path, str::FromStr};
+
+use crate::{json::JsonValue, url::Url};
+
+const META_FILE: &str = "crawl-meta.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrawlMeta {
+    pub seed: Url,
+    pub started_at: u64,
+}
+
+impl CrawlMeta {
+    pub fn read(output_directory: &Path) -> Option<CrawlMeta> {
+        let content = std::fs::read_to_string(output_directory.join(META_FILE)).ok()?;
+        match CrawlMeta::parse(&content) {
+            Ok(meta) => Some(meta),
+            Err(err) => {
+                eprintln!("Ignoring unreadable {META_FILE}: {err}");
+                None
+            }
+        }
+    }
+
+    fn parse(content: &str) -> Result<CrawlMeta, String> {
+        let json = JsonValue::parse(content).map_err(|err| err.to_string())?;
+        let seed = json
+            .get("seed")
+            .and_then(JsonValue::as_str)
+            .ok_or("missing seed".to_owned())?;
+        let started_at = json
+            .get("started_at")
+            .and_then(JsonValue::as_f64)
+            .ok_or("missing started_at".to_owned())?;
+
+        Ok(CrawlMeta {
+            seed: Url::from_str(seed).map_err(|err| err.to_string())?,
+            started_at: started_at as u64,
+        })
+    }
+
+    pub fn write(&self, output_directory: &Path) -> Result<(), String> {
+        let json = JsonValue::Object(vec![
+            ("seed".to_owned(), JsonValue::String(self.seed.to_string())),
+            (
+                "started_at".to_owned(),
+                JsonValue::Number(self.started_at as f64),
+            ),
+        ]);
+
+        std::fs::write(output_directory.join(META_FILE), format!("{json}\n"))
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn check_seed(existing: Option<&CrawlMeta>, seed: &Url, force: bool) -> Result<(), String> {
+        match existing {
+            Some(meta) if meta.seed != *seed => {
+                let message = format!(
+                    "output directory holds a crawl of {} started at {}, not {seed}",
+                    meta.seed, meta.started_at
+                );
+                if force {
+                    eprintln!("Continuing with --force, {message}");
+                    Ok(())
+                } else {
+                    Err(format!("{message} (pass --force to reuse it)"))
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seed_mismatch() {
+        let directory =
+            std::env::temp_dir().join(format!("yoink-crawl-meta-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        assert_eq!(CrawlMeta::read(&directory), None);
+
+        let seed = Url::from_str("https://example.com/docs").unwrap();
+        let other = Url::from_str("https://other.com").unwrap();
+        let meta = CrawlMeta {
+            seed: seed.clone(),
+            started_at: 1_700_000_000,
+        };
+        meta.write(&directory).unwrap();
+        let existing = CrawlMeta::read(&directory);
+        assert_eq!(existing, Some(meta));
+
+        assert!(CrawlMeta::check_seed(None, &other, false).is_ok());
+        assert!(CrawlMeta::check_seed(existing.as_ref(), &seed, false).is_ok());
+        assert!(CrawlMeta::check_seed(existing.as_ref(), &other, false).is_err());
+        assert!(CrawlMeta::check_seed(existing.as_ref(), &other, true).is_ok());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
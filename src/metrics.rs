@@ -0,0 +1,140 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+pub const WRITE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Default)]
+pub struct ByteCounter {
+    total: Arc<AtomicU64>,
+}
+
+impl ByteCounter {
+    pub fn add(&self, bytes: usize) {
+        self.total.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CrawlMetrics {
+    pub processed: usize,
+    pub failed: usize,
+    pub pending: usize,
+    pub processing: usize,
+    pub bytes: u64,
+    pub rate: Option<f64>,
+}
+
+impl CrawlMetrics {
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: &dyn std::fmt::Display| {
+            let _ = writeln!(text, "# HELP yoink_{name} {help}");
+            let _ = writeln!(text, "# TYPE yoink_{name} {kind}");
+            let _ = writeln!(text, "yoink_{name} {value}");
+        };
+
+        metric(
+            "pages_processed_total",
+            "counter",
+            "Pages crawled successfully.",
+            &self.processed,
+        );
+        metric(
+            "pages_failed_total",
+            "counter",
+            "Pages that failed to crawl.",
+            &self.failed,
+        );
+        metric(
+            "pages_pending",
+            "gauge",
+            "Pages waiting in the queue.",
+            &self.pending,
+        );
+        metric(
+            "pages_processing",
+            "gauge",
+            "Pages currently being crawled.",
+            &self.processing,
+        );
+        metric(
+            "downloaded_bytes_total",
+            "counter",
+            "Response body bytes downloaded.",
+            &self.bytes,
+        );
+        if let Some(rate) = self.rate {
+            metric(
+                "pages_per_second",
+                "gauge",
+                "Smoothed rate of finished pages.",
+                &rate,
+            );
+        }
+
+        text
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        fs::write(&temporary, self.to_prometheus()).map_err(|err| err.to_string())?;
+        fs::rename(&temporary, path).map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_text_format() {
+        let metrics = CrawlMetrics {
+            processed: 120,
+            failed: 3,
+            pending: 45,
+            processing: 8,
+            bytes: 9_876_543,
+            rate: Some(2.5),
+        };
+
+        assert_eq!(
+            metrics.to_prometheus(),
+            "# HELP yoink_pages_processed_total Pages crawled successfully.\n\
+             # TYPE yoink_pages_processed_total counter\n\
+             yoink_pages_processed_total 120\n\
+             # HELP yoink_pages_failed_total Pages that failed to crawl.\n\
+             # TYPE yoink_pages_failed_total counter\n\
+             yoink_pages_failed_total 3\n\
+             # HELP yoink_pages_pending Pages waiting in the queue.\n\
+             # TYPE yoink_pages_pending gauge\n\
+             yoink_pages_pending 45\n\
+             # HELP yoink_pages_processing Pages currently being crawled.\n\
+             # TYPE yoink_pages_processing gauge\n\
+             yoink_pages_processing 8\n\
+             # HELP yoink_downloaded_bytes_total Response body bytes downloaded.\n\
+             # TYPE yoink_downloaded_bytes_total counter\n\
+             yoink_downloaded_bytes_total 9876543\n\
+             # HELP yoink_pages_per_second Smoothed rate of finished pages.\n\
+             # TYPE yoink_pages_per_second gauge\n\
+             yoink_pages_per_second 2.5\n"
+        );
+
+        let unknown_rate = CrawlMetrics::default().to_prometheus();
+        assert!(unknown_rate.contains("yoink_pages_processed_total 0\n"));
+        assert!(!unknown_rate.contains("pages_per_second"));
+    }
+}
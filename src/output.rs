@@ -0,0 +1,845 @@
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    fs::{OpenOptions, create_dir_all},
+    io::{self, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::ValueEnum;
+use reqwest::StatusCode;
+use scraper::Html;
+use tokio::{fs::File, io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    encoding::fnv1a_hash,
+    inventory::Discovery,
+    record::RecordSchema,
+    url::{FilenameScheme, Url},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Html,
+    Text,
+    Graph,
+    Inventory,
+    Warc,
+    Null,
+}
+
+pub struct Page<'a> {
+    pub url: &'a Url,
+    pub body: &'a str,
+    pub links: &'a [Url],
+    pub status: u16,
+    pub content_type: Option<&'a str>,
+    pub raw: &'a [u8],
+    pub discovery: Option<Discovery>,
+}
+
+const INVENTORY_HEADER: &str = "url\tstatus\tcontent_type\tbytes\tdepth\tfirst_seen\n";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnExisting {
+    #[default]
+    Overwrite,
+    Skip,
+    Version,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum QueryInFilename {
+    #[default]
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SaveOptions {
+    pub sync: bool,
+    pub filename_scheme: FilenameScheme,
+    pub on_existing: OnExisting,
+    pub query_in_filename: QueryInFilename,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    FilenameTooLong,
+}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{err}"),
+            SaveError::FilenameTooLong => write!(f, "file name too long"),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::InvalidFilename => SaveError::FilenameTooLong,
+            _ => SaveError::Io(err),
+        }
+    }
+}
+
+impl SaveError {
+    pub fn is_storage_full(&self) -> bool {
+        matches!(self, SaveError::Io(err) if err.kind() == ErrorKind::StorageFull)
+    }
+}
+
+pub enum OutputSink {
+    Html {
+        directory: PathBuf,
+        options: SaveOptions,
+    },
+    Text {
+        directory: PathBuf,
+        options: SaveOptions,
+    },
+    Graph {
+        file: Mutex<File>,
+    },
+    Inventory {
+        file: Mutex<File>,
+    },
+    Records {
+        schema: RecordSchema,
+        file: Mutex<File>,
+    },
+    Warc {
+        file: Mutex<File>,
+    },
+    Null,
+}
+
+impl OutputSink {
+    pub fn new(
+        format: OutputFormat,
+        output_directory: &Path,
+        options: &SaveOptions,
+    ) -> Result<Self, String> {
+        match format {
+            OutputFormat::Html => {
+                let directory = output_directory.join("html");
+                create_dir_all(&directory).map_err(|err| err.to_string())?;
+                Ok(OutputSink::Html {
+                    directory,
+                    options: options.to_owned(),
+                })
+            }
+            OutputFormat::Text => {
+                let directory = output_directory.join("text");
+                create_dir_all(&directory).map_err(|err| err.to_string())?;
+                Ok(OutputSink::Text {
+                    directory,
+                    options: options.to_owned(),
+                })
+            }
+            OutputFormat::Graph => {
+                create_dir_all(output_directory).map_err(|err| err.to_string())?;
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_directory.join("graph.tsv"))
+                    .map_err(|err| err.to_string())?;
+                Ok(OutputSink::Graph {
+                    file: Mutex::new(File::from_std(file)),
+                })
+            }
+            OutputFormat::Inventory => {
+                create_dir_all(output_directory).map_err(|err| err.to_string())?;
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_directory.join("inventory.tsv"))
+                    .map_err(|err| err.to_string())?;
+                let is_empty = file.metadata().map_err(|err| err.to_string())?.len() == 0;
+                if is_empty {
+                    file.write_all(INVENTORY_HEADER.as_bytes())
+                        .map_err(|err| err.to_string())?;
+                }
+                Ok(OutputSink::Inventory {
+                    file: Mutex::new(File::from_std(file)),
+                })
+            }
+            OutputFormat::Warc => {
+                create_dir_all(output_directory).map_err(|err| err.to_string())?;
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(output_directory.join("crawl.warc"))
+                    .map_err(|err| err.to_string())?;
+                Ok(OutputSink::Warc {
+                    file: Mutex::new(File::from_std(file)),
+                })
+            }
+            OutputFormat::Null => Ok(OutputSink::Null),
+        }
+    }
+
+    pub fn records(schema: RecordSchema, output_directory: &Path) -> Result<Self, String> {
+        create_dir_all(output_directory).map_err(|err| err.to_string())?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(output_directory.join("records.ndjson"))
+            .map_err(|err| err.to_string())?;
+        Ok(OutputSink::Records {
+            schema,
+            file: Mutex::new(File::from_std(file)),
+        })
+    }
+
+    pub async fn write(&self, page: &Page<'_>) -> Result<(), SaveError> {
+        match self {
+            OutputSink::Html { directory, options } => {
+                save_html(directory, page.url, page.body, options).await
+            }
+            OutputSink::Text { directory, options } => {
+                let text = extract_text(page.body);
+                save_file(directory, page.url, Some("txt"), &text, options).await
+            }
+            OutputSink::Graph { file } => {
+                let edges: String = page
+                    .links
+                    .iter()
+                    .map(|link| format!("{}\t{link}\n", page.url))
+                    .collect();
+
+                let mut file = file.lock().await;
+                file.write_all(edges.as_bytes()).await?;
+                Ok(file.flush().await?)
+            }
+            OutputSink::Inventory { file } => {
+                let row = inventory_row(page);
+
+                let mut file = file.lock().await;
+                file.write_all(row.as_bytes()).await?;
+                Ok(file.flush().await?)
+            }
+            OutputSink::Records { schema, file } => {
+                let record = schema.extract(page.url, &Html::parse_document(page.body));
+                let line = format!("{record}\n");
+
+                let mut file = file.lock().await;
+                file.write_all(line.as_bytes()).await?;
+                Ok(file.flush().await?)
+            }
+            OutputSink::Warc { file } => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                let record_id = warc_record_id(page.url, now.as_nanos());
+                let record = warc_record(page, &warc_date(now.as_secs()), &record_id);
+
+                let mut file = file.lock().await;
+                file.write_all(&record).await?;
+                Ok(file.flush().await?)
+            }
+            OutputSink::Null => Ok(()),
+        }
+    }
+}
+
+fn warc_record(page: &Page<'_>, date: &str, record_id: &str) -> Vec<u8> {
+    let reason = StatusCode::from_u16(page.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or_default();
+    let mut block = format!("HTTP/1.1 {} {reason}\r\n", page.status);
+    if let Some(content_type) = page.content_type {
+        block.push_str(&format!("Content-Type: {content_type}\r\n"));
+    }
+    block.push_str(&format!("Content-Length: {}\r\n\r\n", page.raw.len()));
+    let mut block = block.into_bytes();
+    block.extend_from_slice(page.raw);
+
+    let mut record = format!(
+        "WARC/1.1\r\n\
+         WARC-Type: response\r\n\
+         WARC-Record-ID: <{record_id}>\r\n\
+         WARC-Date: {date}\r\n\
+         WARC-Target-URI: {}\r\n\
+         Content-Type: application/http;msgtype=response\r\n\
+         Content-Length: {}\r\n\r\n",
+        page.url.request_url(),
+        block.len()
+    )
+    .into_bytes();
+    record.extend(block);
+    record.extend_from_slice(b"\r\n\r\n");
+    record
+}
+
+fn warc_record_id(url: &Url, nanos: u128) -> String {
+    let high = fnv1a_hash(url.to_string().as_bytes()) ^ (nanos >> 64) as u64;
+    let low = fnv1a_hash(&nanos.to_le_bytes());
+
+    format!(
+        "urn:uuid:{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0x0fff,
+        ((low >> 48) & 0x3fff) | 0x8000,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+fn warc_date(secs: u64) -> String {
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+
+    // Howard Hinnant's civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+fn inventory_row(page: &Page<'_>) -> String {
+    let (depth, first_seen) = match page.discovery {
+        Some(discovery) => (
+            discovery.depth.to_string(),
+            discovery.first_seen.to_string(),
+        ),
+        None => (String::new(), String::new()),
+    };
+
+    format!(
+        "{}\t{}\t{}\t{}\t{depth}\t{first_seen}\n",
+        page.url,
+        page.status,
+        page.content_type.unwrap_or_default(),
+        page.raw.len()
+    )
+}
+
+pub async fn save_html(
+    html_directory: &Path,
+    url: &Url,
+    html: &str,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    save_file(html_directory, url, None, html, options).await
+}
+
+async fn save_file(
+    directory: &Path,
+    url: &Url,
+    extension: Option<&str>,
+    content: &str,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    let mut file_path = filename_url(url, options.query_in_filename)
+        .storage_path(directory, options.filename_scheme);
+    if let Some(extension) = extension {
+        file_path.set_extension(extension);
+    }
+    let Some(file_path) = target_path(file_path, options.on_existing).await? else {
+        return Ok(());
+    };
+
+    if let Some(parent) = file_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let temp_path = temp_path(&file_path);
+    let mut result = write_temp_file(&temp_path, content, options.sync).await;
+    if result.is_ok() {
+        result = rename_into_place(&temp_path, &file_path).await;
+    }
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp_path).await;
+    }
+
+    result
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".tmp");
+    path.with_file_name(file_name)
+}
+
+async fn write_temp_file(temp_path: &Path, content: &str, sync: bool) -> Result<(), SaveError> {
+    let mut file = File::create(temp_path).await?;
+    file.write_all(content.as_bytes()).await?;
+    file.flush().await?;
+
+    if sync {
+        file.sync_all().await?;
+    }
+
+    Ok(())
+}
+
+async fn rename_into_place(temp_path: &Path, file_path: &Path) -> Result<(), SaveError> {
+    match tokio::fs::rename(temp_path, file_path).await {
+        Err(err) if err.kind() == ErrorKind::CrossesDevices => {
+            tokio::fs::copy(temp_path, file_path).await?;
+            Ok(tokio::fs::remove_file(temp_path).await?)
+        }
+        result => Ok(result?),
+    }
+}
+
+fn filename_url(url: &Url, query_in_filename: QueryInFilename) -> Cow<'_, Url> {
+    match (query_in_filename, url.query.as_deref()) {
+        (QueryInFilename::Off, Some(query)) => {
+            let hash = fnv1a_hash(query.as_bytes());
+            let path = url.path.as_deref().unwrap_or_default();
+            Cow::Owned(Url {
+                path: Some(format!("{path}-{:08x}", hash >> 32)),
+                query: None,
+                ..url.to_owned()
+            })
+        }
+        _ => Cow::Borrowed(url),
+    }
+}
+
+async fn target_path(path: PathBuf, on_existing: OnExisting) -> Result<Option<PathBuf>, SaveError> {
+    match on_existing {
+        OnExisting::Overwrite => Ok(Some(path)),
+        OnExisting::Skip if file_exists(&path).await? => Ok(None),
+        OnExisting::Skip => Ok(Some(path)),
+        OnExisting::Version => {
+            if !file_exists(&path).await? {
+                return Ok(Some(path));
+            }
+
+            let mut version = 1;
+            loop {
+                let versioned = versioned_path(&path, version);
+                if !file_exists(&versioned).await? {
+                    return Ok(Some(versioned));
+                }
+                version += 1;
+            }
+        }
+    }
+}
+
+async fn file_exists(path: &Path) -> Result<bool, SaveError> {
+    Ok(tokio::fs::try_exists(path).await?)
+}
+
+fn versioned_path(path: &Path, version: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    match path.extension() {
+        Some(extension) => {
+            path.with_file_name(format!("{stem}.{version}.{}", extension.to_string_lossy()))
+        }
+        None => path.with_file_name(format!("{stem}.{version}")),
+    }
+}
+
+fn extract_text(body: &str) -> String {
+    let document = Html::parse_document(body);
+
+    document
+        .root_element()
+        .descendants()
+        .filter(|node| {
+            node.parent()
+                .and_then(|parent| parent.value().as_element().map(|el| el.name()))
+                .is_none_or(|name| name != "script" && name != "style")
+        })
+        .filter_map(|node| node.value().as_text())
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::encoding::url_encode;
+
+    fn test_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("yoink-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[tokio::test]
+    async fn test_multiple_sinks_receive_page() {
+        let directory = test_directory("multiple-sinks");
+        let options = SaveOptions::default();
+        let sinks = [
+            OutputSink::new(OutputFormat::Html, &directory, &options).unwrap(),
+            OutputSink::new(OutputFormat::Text, &directory, &options).unwrap(),
+            OutputSink::new(OutputFormat::Graph, &directory, &options).unwrap(),
+            OutputSink::new(OutputFormat::Warc, &directory, &options).unwrap(),
+        ];
+
+        let url = Url::from_str("https://example.com/foo").unwrap();
+        let links = [Url::from_str("https://example.com/bar").unwrap()];
+        let page = Page {
+            url: &url,
+            body: "<html><body><p>Hello</p><script>ignored()</script><a href=\"/bar\">Bar</a></body></html>",
+            links: &links,
+            status: 200,
+            content_type: Some("text/html"),
+            raw: b"<html><body><p>Hello</p><script>ignored()</script><a href=\"/bar\">Bar</a></body></html>",
+            discovery: None,
+        };
+
+        for sink in &sinks {
+            sink.write(&page).await.unwrap();
+        }
+
+        let encoded_url = url_encode("https://example.com/foo");
+        let html =
+            std::fs::read_to_string(directory.join("html").join(format!("{encoded_url}.html")));
+        assert_eq!(html.unwrap(), page.body);
+
+        let text =
+            std::fs::read_to_string(directory.join("text").join(format!("{encoded_url}.txt")));
+        assert_eq!(text.unwrap(), "Hello\nBar");
+
+        let graph = std::fs::read_to_string(directory.join("graph.tsv")).unwrap();
+        assert_eq!(graph, "https://example.com/foo\thttps://example.com/bar\n");
+
+        let warc = std::fs::read(directory.join("crawl.warc")).unwrap();
+        assert!(warc.starts_with(b"WARC/1.1\r\nWARC-Type: response\r\n"));
+        assert!(warc.ends_with(&[page.raw, b"\r\n\r\n"].concat()));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_null_sink_writes_nothing() {
+        let directory = test_directory("null-sink");
+        let sink =
+            OutputSink::new(OutputFormat::Null, &directory, &SaveOptions::default()).unwrap();
+
+        let url = Url::from_str("https://example.com/foo").unwrap();
+        let page = Page {
+            url: &url,
+            body: "<html><body>discarded</body></html>",
+            links: &[],
+            status: 200,
+            content_type: Some("text/html"),
+            raw: b"<html><body>discarded</body></html>",
+            discovery: None,
+        };
+
+        assert!(sink.write(&page).await.is_ok());
+        assert!(!directory.exists());
+    }
+
+    #[tokio::test]
+    async fn test_save_html_fully_written_on_success() {
+        let directory = test_directory("save-html-flush");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        let url = Url::from_str("https://example.com/large").unwrap();
+        let html = "<p>yoink</p>".repeat(100_000);
+        let encoded_url = url_encode("https://example.com/large");
+
+        for sync in [false, true] {
+            let options = SaveOptions {
+                sync,
+                ..Default::default()
+            };
+            save_html(&directory, &url, &html, &options).await.unwrap();
+
+            let saved = std::fs::read_to_string(directory.join(format!("{encoded_url}.html")));
+            assert_eq!(saved.unwrap(), html);
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_storage_path_matches_save_html() {
+        let directory = test_directory("storage-path");
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for url in [
+            "https://example.com",
+            "https://example.com/foo/bar",
+            "https://example.com/a.html",
+        ] {
+            let url = Url::from_str(url).unwrap();
+            save_html(&directory, &url, "<html></html>", &SaveOptions::default())
+                .await
+                .unwrap();
+
+            let path = url.storage_path(&directory, FilenameScheme::default());
+            let encoded_url = url_encode(&url.to_string());
+            assert_eq!(path, directory.join(format!("{encoded_url}.html")));
+            assert!(path.exists());
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_errors() {
+        let directory = test_directory("save-errors");
+        std::fs::create_dir_all(&directory).unwrap();
+        let long = Url::from_str(&format!("https://example.com/{}", "a".repeat(300))).unwrap();
+        assert!(matches!(
+            save_html(&directory, &long, "<html></html>", &SaveOptions::default()).await,
+            Err(SaveError::FilenameTooLong)
+        ));
+
+        let blocked = directory.join("blocked");
+        std::fs::write(&blocked, "not a directory").unwrap();
+        let url = Url::from_str("https://example.com/page").unwrap();
+        let err = save_html(&blocked, &url, "<html></html>", &SaveOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, SaveError::Io(_)));
+        assert!(!err.is_storage_full());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_save_storage_full() {
+        let directory = test_directory("storage-full");
+        std::fs::create_dir_all(&directory).unwrap();
+        let url = Url::from_str("https://example.com/page").unwrap();
+        let options = SaveOptions::default();
+        let file_path = url.storage_path(&directory, options.filename_scheme);
+        std::os::unix::fs::symlink("/dev/full", temp_path(&file_path)).unwrap();
+
+        let err = save_html(&directory, &url, "<html></html>", &options)
+            .await
+            .unwrap_err();
+        assert!(err.is_storage_full());
+        assert!(!file_path.exists());
+        assert!(!temp_path(&file_path).exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_save_keeps_final_file_intact() {
+        let directory = test_directory("atomic-save");
+        let url = Url::from_str("https://example.com/page").unwrap();
+        let options = SaveOptions::default();
+        let file_path = url.storage_path(&directory, options.filename_scheme);
+
+        save_html(&directory, &url, "<html>first</html>", &options)
+            .await
+            .unwrap();
+        assert!(!temp_path(&file_path).exists());
+
+        std::fs::create_dir(temp_path(&file_path)).unwrap();
+        assert!(
+            save_html(&directory, &url, "<html>second</html>", &options)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "<html>first</html>"
+        );
+
+        let other = Url::from_str("https://example.com/other").unwrap();
+        let other_path = other.storage_path(&directory, options.filename_scheme);
+        std::fs::create_dir(temp_path(&other_path)).unwrap();
+        assert!(
+            save_html(&directory, &other, "<html></html>", &options)
+                .await
+                .is_err()
+        );
+        assert!(!other_path.exists());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_versioned_path() {
+        assert_eq!(
+            versioned_path(Path::new("out/name.html"), 1),
+            Path::new("out/name.1.html")
+        );
+        assert_eq!(
+            versioned_path(Path::new("out/name.html"), 12),
+            Path::new("out/name.12.html")
+        );
+        assert_eq!(
+            versioned_path(Path::new("out/name"), 2),
+            Path::new("out/name.2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_existing_policies() {
+        let url = Url::from_str("https://example.com/page").unwrap();
+
+        for (policy, expected) in [
+            (OnExisting::Overwrite, vec!["second"]),
+            (OnExisting::Skip, vec!["first"]),
+            (OnExisting::Version, vec!["first", "second", "third"]),
+        ] {
+            let directory = test_directory(&format!("on-existing-{policy:?}"));
+            std::fs::create_dir_all(&directory).unwrap();
+            let options = SaveOptions {
+                on_existing: policy,
+                ..Default::default()
+            };
+
+            save_html(&directory, &url, "first", &options)
+                .await
+                .unwrap();
+            save_html(&directory, &url, "second", &options)
+                .await
+                .unwrap();
+            if policy == OnExisting::Version {
+                save_html(&directory, &url, "third", &options)
+                    .await
+                    .unwrap();
+            }
+
+            let path = url.storage_path(&directory, options.filename_scheme);
+            let mut saved = vec![std::fs::read_to_string(&path).unwrap()];
+            for version in 1.. {
+                match std::fs::read_to_string(versioned_path(&path, version)) {
+                    Ok(content) => saved.push(content),
+                    Err(_) => break,
+                }
+            }
+
+            assert_eq!(saved, expected, "{policy:?}");
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_query_in_filename() {
+        let directory = Path::new("out");
+        let filename = |url: &str, query_in_filename| {
+            let url = Url::from_str(url).unwrap();
+            filename_url(&url, query_in_filename).storage_path(directory, FilenameScheme::Encoded)
+        };
+
+        let with_query = filename(
+            "https://example.com/search?q=rust&page=2",
+            QueryInFilename::On,
+        );
+        assert_eq!(
+            with_query,
+            directory.join(format!(
+                "{}.html",
+                url_encode("https://example.com/search?q=rust&page=2")
+            ))
+        );
+
+        let without_query = filename(
+            "https://example.com/search?q=rust&page=2",
+            QueryInFilename::Off,
+        );
+        let hash = fnv1a_hash(b"q=rust&page=2") >> 32;
+        assert_eq!(
+            without_query,
+            directory.join(format!(
+                "{}.html",
+                url_encode(&format!("https://example.com/search-{hash:08x}"))
+            ))
+        );
+        assert_ne!(
+            without_query,
+            filename("https://example.com/search?q=go", QueryInFilename::Off)
+        );
+
+        assert_eq!(
+            filename("https://example.com/plain", QueryInFilename::Off),
+            filename("https://example.com/plain", QueryInFilename::On)
+        );
+    }
+
+    #[test]
+    fn test_warc_record() {
+        let url = Url::from_str("https://example.com/docs").unwrap();
+        let page = Page {
+            url: &url,
+            body: "<p>docs</p>",
+            links: &[],
+            status: 404,
+            content_type: Some("text/html"),
+            raw: b"<p>docs</p>",
+            discovery: None,
+        };
+
+        let record = warc_record(&page, "2023-11-14T22:13:20Z", "urn:uuid:test");
+        assert_eq!(
+            String::from_utf8(record).unwrap(),
+            "WARC/1.1\r\n\
+             WARC-Type: response\r\n\
+             WARC-Record-ID: <urn:uuid:test>\r\n\
+             WARC-Date: 2023-11-14T22:13:20Z\r\n\
+             WARC-Target-URI: https://example.com/docs\r\n\
+             Content-Type: application/http;msgtype=response\r\n\
+             Content-Length: 82\r\n\r\n\
+             HTTP/1.1 404 Not Found\r\n\
+             Content-Type: text/html\r\n\
+             Content-Length: 11\r\n\r\n\
+             <p>docs</p>\r\n\r\n"
+        );
+
+        assert_eq!(warc_date(0), "1970-01-01T00:00:00Z");
+        assert_eq!(warc_date(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(warc_date(951_827_696), "2000-02-29T12:34:56Z");
+
+        let id = warc_record_id(&url, 1);
+        assert_eq!(id.len(), "urn:uuid:".len() + 36);
+        assert_ne!(id, warc_record_id(&url, 2));
+    }
+
+    #[test]
+    fn test_inventory_row() {
+        let url = Url::from_str("https://example.com/docs").unwrap();
+        let mut page = Page {
+            url: &url,
+            body: "<p>docs</p>",
+            links: &[],
+            status: 200,
+            content_type: Some("text/html; charset=utf-8"),
+            raw: b"<p>docs</p>",
+            discovery: Some(Discovery {
+                depth: 2,
+                first_seen: 1_700_000_000,
+            }),
+        };
+        assert_eq!(
+            inventory_row(&page),
+            "https://example.com/docs\t200\ttext/html; charset=utf-8\t11\t2\t1700000000\n"
+        );
+
+        page.content_type = None;
+        page.discovery = None;
+        assert_eq!(
+            inventory_row(&page),
+            "https://example.com/docs\t200\t\t11\t\t\n"
+        );
+    }
+}
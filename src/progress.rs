@@ -0,0 +1,103 @@
+use std::time::{Duration, Instant};
+
+const SMOOTHING: f64 = 0.3;
+const MIN_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Progress {
+    last_sample: Instant,
+    last_finished: usize,
+    rate: Option<f64>,
+}
+
+impl Progress {
+    pub fn new(finished: usize, now: Instant) -> Self {
+        Progress {
+            last_sample: now,
+            last_finished: finished,
+            rate: None,
+        }
+    }
+
+    pub fn update(&mut self, finished: usize, now: Instant) {
+        let elapsed = now.duration_since(self.last_sample);
+        if elapsed < MIN_SAMPLE_INTERVAL {
+            return;
+        }
+
+        let sample = finished.saturating_sub(self.last_finished) as f64 / elapsed.as_secs_f64();
+        self.rate = Some(match self.rate {
+            Some(rate) => SMOOTHING * sample + (1.0 - SMOOTHING) * rate,
+            None => sample,
+        });
+        self.last_sample = now;
+        self.last_finished = finished;
+    }
+
+    pub fn rate(&self) -> Option<f64> {
+        self.rate
+    }
+
+    pub fn status(&self, remaining: usize) -> String {
+        match self.rate.and_then(|rate| eta(remaining, rate)) {
+            Some(eta) => format!(
+                "ETA: {} ({:.1} pages/s, {remaining} remaining)",
+                format_duration(eta),
+                self.rate.unwrap_or_default()
+            ),
+            None => format!("ETA: unknown ({remaining} remaining)"),
+        }
+    }
+}
+
+pub fn eta(remaining: usize, rate: f64) -> Option<Duration> {
+    if rate <= 0.0 || !rate.is_finite() {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(remaining as f64 / rate))
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m {seconds:02}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds:02}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_computation() {
+        assert_eq!(eta(100, 4.0), Some(Duration::from_secs(25)));
+        assert_eq!(eta(0, 4.0), Some(Duration::ZERO));
+        assert_eq!(eta(100, 0.0), None);
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 02m 03s");
+        assert_eq!(format_duration(Duration::from_secs(83)), "1m 23s");
+        assert_eq!(format_duration(Duration::from_secs(7)), "7s");
+    }
+
+    #[test]
+    fn test_rate_smoothing() {
+        let start = Instant::now();
+        let mut progress = Progress::new(0, start);
+        assert_eq!(progress.rate, None);
+
+        progress.update(5, start + Duration::from_millis(500));
+        assert_eq!(progress.rate, None);
+
+        progress.update(10, start + Duration::from_secs(1));
+        assert_eq!(progress.rate, Some(10.0));
+
+        progress.update(30, start + Duration::from_secs(2));
+        assert!((progress.rate.unwrap() - 13.0).abs() < 1e-9);
+        assert_eq!(progress.status(26), "ETA: 2s (13.0 pages/s, 26 remaining)");
+    }
+}
@@ -1,13 +1,27 @@
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::Duration,
+};
 
-use crate::url::Url;
+use crate::{
+    journal::JournalHistory,
+    refresh::{is_stale, refresh_in},
+    url::Url,
+};
 
 pub struct Queue {
     pending: VecDeque<Url>,
     pending_set: HashSet<Url>,
     processing: HashSet<Url>,
-    processed: HashSet<Url>,
+    processed: HashMap<Url, u64>,
     failed: HashSet<Url>,
+    auth_required: HashSet<Url>,
+    attempts: HashMap<Url, u32>,
+    folded_paths: Option<HashSet<Url>>,
+    depths: HashMap<Url, u32>,
+    max_depth: Option<u32>,
+    hosts: HashSet<String>,
+    max_hosts: Option<usize>,
 }
 
 impl Queue {
@@ -15,56 +29,277 @@ impl Queue {
         base_url: &Url,
         pending: Vec<Url>,
         processing: Vec<Url>,
-        processed: Vec<Url>,
+        processed: Vec<(Url, u64)>,
         failed: Vec<Url>,
     ) -> Self {
         let mut queue = Queue {
             pending: pending.clone().into_iter().collect(),
             pending_set: pending.iter().cloned().collect(),
             processing: processing.iter().cloned().collect(),
-            processed: processed.iter().cloned().collect(),
+            processed: processed.into_iter().collect(),
             failed: failed.iter().cloned().collect(),
+            auth_required: HashSet::new(),
+            attempts: HashMap::new(),
+            folded_paths: None,
+            depths: HashMap::new(),
+            max_depth: None,
+            hosts: HashSet::new(),
+            max_hosts: None,
         };
+        queue.hosts = queue
+            .pending
+            .iter()
+            .chain(&queue.processing)
+            .chain(queue.processed.keys())
+            .chain(&queue.failed)
+            .map(|url| url.host.clone())
+            .collect();
 
         queue.add_pending(base_url);
 
         queue
     }
 
-    pub fn add_pending(&mut self, url: &Url) {
-        if !self.pending_set.contains(url)
-            && !self.processed.contains(url)
-            && !self.processing.contains(url)
-        {
-            self.pending.push_back(url.to_owned());
-            self.pending_set.insert(url.to_owned());
+    pub fn fold_path_case(&mut self) {
+        let folded = self
+            .pending
+            .iter()
+            .chain(&self.processing)
+            .chain(self.processed.keys())
+            .map(Url::with_lowercase_path)
+            .collect();
+        self.folded_paths = Some(folded);
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: u32) {
+        self.max_depth = Some(max_depth);
+    }
+
+    pub fn exceeds_max_depth(&self, depth: u32) -> bool {
+        self.max_depth.is_some_and(|max_depth| depth > max_depth)
+    }
+
+    pub fn set_max_hosts(&mut self, max_hosts: usize) {
+        self.max_hosts = Some(max_hosts);
+    }
+
+    pub fn admits_host(&self, host: &str) -> bool {
+        self.max_hosts
+            .is_none_or(|max_hosts| self.hosts.contains(host) || self.hosts.len() < max_hosts)
+    }
+
+    pub fn depth(&self, url: &Url) -> u32 {
+        self.depths.get(url).copied().unwrap_or(0)
+    }
+
+    pub fn add_pending(&mut self, url: &Url) -> bool {
+        self.add_pending_with_depth(url, 0)
+    }
+
+    pub fn add_pending_with_depth(&mut self, url: &Url, depth: u32) -> bool {
+        if !self.admit(url, depth) {
+            return false;
+        }
+
+        self.pending.push_back(url.to_owned());
+        self.pending_set.insert(url.to_owned())
+    }
+
+    pub fn add_pending_priority(&mut self, url: &Url, depth: u32) -> bool {
+        if !self.admit(url, depth) {
+            return false;
+        }
+
+        self.pending.push_front(url.to_owned());
+        self.pending_set.insert(url.to_owned())
+    }
+
+    fn admit(&mut self, url: &Url, depth: u32) -> bool {
+        if self.is_known(url) || self.exceeds_max_depth(depth) || !self.admits_host(&url.host) {
+            return false;
+        }
+        self.record_folded(url);
+        self.depths.entry(url.to_owned()).or_insert(depth);
+        self.hosts.insert(url.host.clone());
+
+        true
+    }
+
+    pub fn promote(&mut self, url: &Url) -> bool {
+        let Some(index) = self.pending.iter().position(|pending| pending == url) else {
+            return false;
+        };
+
+        if let Some(url) = self.pending.remove(index) {
+            self.pending.push_front(url);
+        }
+        true
+    }
+
+    pub fn claim(&mut self, url: &Url, depth: u32) -> bool {
+        if self.processed.contains_key(url) || self.processing.contains(url) {
+            return false;
+        }
+        if self.pending_set.remove(url) {
+            self.pending.retain(|pending| pending != url);
+        }
+        self.depths
+            .entry(url.to_owned())
+            .and_modify(|known| *known = (*known).min(depth))
+            .or_insert(depth);
+
+        self.processing.insert(url.to_owned())
+    }
+
+    fn is_known(&self, url: &Url) -> bool {
+        self.pending_set.contains(url)
+            || self.processed.contains_key(url)
+            || self.processing.contains(url)
+            || self.failed.contains(url)
+            || self
+                .folded_paths
+                .as_ref()
+                .is_some_and(|folded| folded.contains(&url.with_lowercase_path()))
+    }
+
+    fn record_folded(&mut self, url: &Url) {
+        if let Some(folded) = &mut self.folded_paths {
+            folded.insert(url.with_lowercase_path());
         }
     }
 
-    pub fn next(&mut self) -> Option<Url> {
+    pub fn next(&mut self) -> Option<(Url, u32)> {
         if let Some(url) = self.pending.pop_front() {
             self.pending_set.remove(&url);
             self.processing.insert(url.clone());
 
-            return Some(url);
+            let depth = self.depth(&url);
+            return Some((url, depth));
         }
 
         None
     }
 
-    pub fn mark_as_processed(&mut self, url: &Url) {
+    pub fn pending(&self) -> impl Iterator<Item = &Url> {
+        self.pending.iter()
+    }
+
+    pub fn processing(&self) -> impl Iterator<Item = &Url> {
+        self.processing.iter()
+    }
+
+    pub fn restore_attempts(&mut self, attempts: HashMap<Url, u32>) {
+        self.attempts.extend(attempts);
+    }
+
+    pub fn attempts(&self, url: &Url) -> u32 {
+        self.attempts.get(url).copied().unwrap_or(0)
+    }
+
+    pub fn retry(&mut self, url: &Url) -> u32 {
         self.processing.remove(url);
-        self.processed.insert(url.to_owned());
+        self.pending.push_back(url.to_owned());
+        self.pending_set.insert(url.to_owned());
+
+        let attempts = self.attempts.entry(url.to_owned()).or_insert(0);
+        *attempts += 1;
+        *attempts
+    }
+
+    pub fn total_finished(&self) -> usize {
+        self.processed.len() + self.failed.len()
+    }
+
+    pub fn processed_count(&self) -> usize {
+        self.processed.len()
+    }
+
+    pub fn failed_count(&self) -> usize {
+        self.failed.len()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.pending.len() + self.processing.len()
+    }
+
+    pub fn mark_as_processed(&mut self, url: &Url, at: u64) {
+        self.processing.remove(url);
+        self.processed.insert(url.to_owned(), at);
+    }
+
+    pub fn refresh_stale(&mut self, now: u64, refresh_after: Duration) -> Vec<Url> {
+        let stale: Vec<Url> = self
+            .processed
+            .iter()
+            .filter(|(_, at)| is_stale(**at, now, refresh_after))
+            .map(|(url, _)| url.to_owned())
+            .collect();
+
+        for url in &stale {
+            self.processed.remove(url);
+            self.pending.push_back(url.to_owned());
+            self.pending_set.insert(url.to_owned());
+        }
+
+        stale
+    }
+
+    pub fn requeue_processing_as_pending(&mut self) -> Vec<Url> {
+        let mut requeued = Vec::new();
+        for url in std::mem::take(&mut self.processing) {
+            if self.processed.contains_key(&url)
+                || self.failed.contains(&url)
+                || self.pending_set.contains(&url)
+            {
+                continue;
+            }
+
+            self.pending.push_back(url.to_owned());
+            self.pending_set.insert(url.to_owned());
+            requeued.push(url);
+        }
+
+        requeued
+    }
+
+    pub fn next_refresh_in(&self, now: u64, refresh_after: Duration) -> Option<Duration> {
+        self.processed
+            .values()
+            .map(|at| refresh_in(*at, now, refresh_after))
+            .min()
     }
 
     pub fn mark_as_failed(&mut self, url: &Url) {
         self.processing.remove(url);
         self.failed.insert(url.to_owned());
+        if let Some(folded) = &mut self.folded_paths {
+            folded.remove(&url.with_lowercase_path());
+        }
+    }
+
+    pub fn mark_as_auth_required(&mut self, url: &Url) {
+        self.mark_as_failed(url);
+        self.auth_required.insert(url.to_owned());
+    }
+
+    pub fn snapshot(&self) -> JournalHistory {
+        JournalHistory {
+            pending: self.pending.iter().cloned().collect(),
+            processing: self.processing.iter().cloned().collect(),
+            processed: self
+                .processed
+                .iter()
+                .map(|(url, at)| (url.to_owned(), *at))
+                .collect(),
+            failed: self.failed.iter().cloned().collect(),
+            attempts: self.attempts.clone(),
+            ..JournalHistory::default()
+        }
     }
 
     pub fn print_summary(&self) {
         println!(
-            "Total: {}, pending: {}, processing: {}, processed: {}, failed: {}",
+            "Total: {}, pending: {}, processing: {}, processed: {}, failed: {}, auth required: {}",
             self.pending_set.len()
                 + self.processing.len()
                 + self.processed.len()
@@ -72,7 +307,196 @@ impl Queue {
             self.pending.len(),
             self.processing.len(),
             self.processed.len(),
-            self.failed.len()
+            self.failed.len(),
+            self.auth_required.len()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_priority_url_dequeued_first() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+
+        let normal = Url::from_str("https://example.com/page").unwrap();
+        let priority = Url::from_str("https://example.com/category").unwrap();
+        queue.add_pending(&normal);
+        queue.add_pending_priority(&priority, 1);
+
+        assert_eq!(queue.next(), Some((priority, 1)));
+        assert_eq!(queue.next(), Some((base, 0)));
+        assert_eq!(queue.next(), Some((normal, 0)));
+        assert_eq!(queue.next(), None);
+    }
+
+    #[test]
+    fn test_promoted_canonical_dequeued_first() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        assert_eq!(queue.next(), Some((base, 0)));
+
+        let duplicate = Url::from_str("https://example.com/article?utm_source=feed").unwrap();
+        let canonical = Url::from_str("https://example.com/article").unwrap();
+        queue.add_pending(&duplicate);
+        queue.add_pending(&canonical);
+        assert!(queue.promote(&canonical));
+        assert!(!queue.promote(&Url::from_str("https://example.com/unknown").unwrap()));
+
+        assert_eq!(queue.next(), Some((canonical, 0)));
+        assert_eq!(queue.next(), Some((duplicate, 0)));
+        assert_eq!(queue.next(), None);
+    }
+
+    #[test]
+    fn test_depth_tracking() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        queue.set_max_depth(2);
+
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+        assert!(queue.add_pending_with_depth(&url("a"), 1));
+        assert!(queue.add_pending_with_depth(&url("a/b"), 2));
+        assert!(!queue.add_pending_with_depth(&url("a/b/c"), 3));
+        assert!(!queue.add_pending_priority(&url("a/b/d"), 3));
+        assert!(!queue.add_pending_with_depth(&url("a"), 2));
+
+        assert_eq!(queue.next(), Some((base, 0)));
+        assert_eq!(queue.next(), Some((url("a"), 1)));
+        assert_eq!(queue.depth(&url("a")), 1);
+        assert_eq!(queue.next(), Some((url("a/b"), 2)));
+        assert_eq!(queue.next(), None);
+
+        assert!(queue.claim(&url("redirected"), queue.depth(&url("a/b"))));
+        assert_eq!(queue.depth(&url("redirected")), 2);
+        assert!(queue.exceeds_max_depth(queue.depth(&url("redirected")) + 1));
+    }
+
+    #[test]
+    fn test_max_hosts() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        queue.set_max_hosts(2);
+
+        let url = |url: &str| Url::from_str(url).unwrap();
+        assert!(queue.add_pending(&url("https://docs.example.com/intro")));
+        assert!(!queue.admits_host("blog.example.com"));
+        assert!(!queue.add_pending(&url("https://blog.example.com/post")));
+        assert!(queue.add_pending(&url("https://example.com/about")));
+        assert!(queue.add_pending(&url("https://docs.example.com/guide")));
+
+        let pending: Vec<String> = queue.pending().map(Url::to_string).collect();
+        assert_eq!(
+            pending,
+            vec![
+                "https://example.com",
+                "https://docs.example.com/intro",
+                "https://example.com/about",
+                "https://docs.example.com/guide",
+            ]
         );
     }
+
+    #[test]
+    fn test_fold_path_case() {
+        let base = Url::from_str("https://example.com/Docs").unwrap();
+        let variants = ["https://example.com/docs", "https://example.com/DOCS"]
+            .map(|url| Url::from_str(url).unwrap());
+        let query_variant = Url::from_str("https://example.com/docs?Q=1").unwrap();
+
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        for url in &variants {
+            assert!(queue.add_pending(url));
+        }
+
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+        queue.fold_path_case();
+        for url in &variants {
+            assert!(!queue.add_pending(url));
+        }
+        assert!(queue.add_pending(&query_variant));
+        assert!(!queue.add_pending(&Url::from_str("https://example.com/DOCS?Q=1").unwrap()));
+        assert!(!queue.add_pending(&Url::from_str("https://example.com/docs?Q=1").unwrap()));
+        assert!(queue.add_pending(&Url::from_str("https://example.com/docs?q=1").unwrap()));
+
+        assert_eq!(queue.next(), Some((base, 0)));
+    }
+
+    #[test]
+    fn test_retry_requeues_and_counts_attempts() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let mut queue = Queue::new_with_initial(&base, vec![], vec![], vec![], vec![]);
+
+        let (url, _) = queue.next().unwrap();
+        assert_eq!(queue.attempts(&url), 0);
+        assert_eq!(queue.retry(&url), 1);
+        assert_eq!(queue.next(), Some((url.clone(), 0)));
+        assert_eq!(queue.retry(&url), 2);
+        assert_eq!(queue.attempts(&url), 2);
+    }
+
+    #[test]
+    fn test_requeue_processing_as_pending() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let url = |path: &str| Url::from_str(&format!("https://example.com/{path}")).unwrap();
+        let mut queue = Queue::new_with_initial(
+            &base,
+            vec![url("pending")],
+            vec![
+                url("interrupted"),
+                url("done"),
+                url("broken"),
+                url("pending"),
+            ],
+            vec![(url("done"), 100)],
+            vec![url("broken")],
+        );
+
+        assert_eq!(
+            queue.requeue_processing_as_pending(),
+            vec![url("interrupted")]
+        );
+        assert_eq!(queue.processing().count(), 0);
+        let pending: Vec<&Url> = queue.pending().collect();
+        assert_eq!(pending, vec![&url("pending"), &base, &url("interrupted")]);
+        assert!(queue.requeue_processing_as_pending().is_empty());
+    }
+
+    #[test]
+    fn test_refresh_stale_requeues_old_pages() {
+        let base = Url::from_str("https://example.com").unwrap();
+        let old = Url::from_str("https://example.com/old").unwrap();
+        let fresh = Url::from_str("https://example.com/fresh").unwrap();
+        let mut queue = Queue::new_with_initial(
+            &base,
+            vec![],
+            vec![],
+            vec![
+                (base.clone(), 900),
+                (old.clone(), 100),
+                (fresh.clone(), 950),
+            ],
+            vec![],
+        );
+        assert_eq!(queue.next(), None);
+
+        let refresh_after = Duration::from_secs(500);
+        assert_eq!(queue.refresh_stale(1000, refresh_after), vec![old.clone()]);
+        assert_eq!(queue.next(), Some((old.clone(), 0)));
+        assert_eq!(queue.next(), None);
+        assert_eq!(
+            queue.next_refresh_in(1000, refresh_after),
+            Some(Duration::from_secs(400))
+        );
+
+        queue.mark_as_processed(&old, 1000);
+        let mut stale = queue.refresh_stale(1450, refresh_after);
+        stale.sort_by_key(|url| url.to_string());
+        assert_eq!(stale, vec![base, fresh]);
+    }
 }
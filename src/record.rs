@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use scraper::{Html, Selector};
+
+use crate::{json::JsonValue, url::Url};
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    selector: Selector,
+    attribute: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordSchema {
+    fields: Vec<Field>,
+}
+
+impl RecordSchema {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let JsonValue::Object(entries) =
+            JsonValue::parse(content).map_err(|err| err.to_string())?
+        else {
+            return Err("record schema must be a json object".to_owned());
+        };
+
+        let mut fields = Vec::new();
+        for (name, value) in entries {
+            if name == "url" {
+                return Err("record schema field \"url\" is reserved".to_owned());
+            }
+            let Some(spec) = value.as_str() else {
+                return Err(format!("record schema field {name:?} must be a string"));
+            };
+            let (selector, attribute) = match spec.rsplit_once('@') {
+                Some((selector, attribute)) => (selector, Some(attribute.to_owned())),
+                None => (spec, None),
+            };
+            let selector = Selector::parse(selector)
+                .map_err(|err| format!("record schema field {name:?}: {err}"))?;
+
+            fields.push(Field {
+                name,
+                selector,
+                attribute,
+            });
+        }
+
+        Ok(RecordSchema { fields })
+    }
+
+    pub fn extract(&self, url: &Url, document: &Html) -> JsonValue {
+        let mut record = vec![("url".to_owned(), JsonValue::String(url.to_string()))];
+
+        for field in &self.fields {
+            let mut values: Vec<JsonValue> = document
+                .select(&field.selector)
+                .filter_map(|element| match &field.attribute {
+                    Some(attribute) => element.attr(attribute).map(str::to_owned),
+                    None => Some(element.text().collect::<String>().trim().to_owned()),
+                })
+                .map(JsonValue::String)
+                .collect();
+
+            let value = match values.len() {
+                0 => JsonValue::Null,
+                1 => values.remove(0),
+                _ => JsonValue::Array(values),
+            };
+            record.push((field.name.clone(), value));
+        }
+
+        JsonValue::Object(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_record() {
+        let schema = RecordSchema::parse(
+            r#"{"title": "h1", "image": "img.hero@src", "tags": "li.tag", "price": ".price"}"#,
+        )
+        .unwrap();
+        let document = Html::parse_document(
+            r#"<html><body><h1> Rust Book </h1><img class="hero" src="/cover.png">
+            <ul><li class="tag">rust</li><li class="tag">books</li></ul></body></html>"#,
+        );
+        let url = Url::from_str("https://example.com/book").unwrap();
+
+        assert_eq!(
+            schema.extract(&url, &document).to_string(),
+            r#"{"url":"https://example.com/book","title":"Rust Book","image":"/cover.png","tags":["rust","books"],"price":null}"#
+        );
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        assert!(RecordSchema::parse(r#"["h1"]"#).is_err());
+        assert!(RecordSchema::parse(r#"{"title": 1}"#).is_err());
+        assert!(RecordSchema::parse(r#"{"title": "[[["}"#).is_err());
+        assert!(RecordSchema::parse(r#"{"url": "a@href"}"#).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+use regex::Regex;
+use reqwest::{Response, StatusCode, redirect::Policy};
+
+use crate::{
+    scope::{Scope, ScopeRule, in_scope},
+    url::{Url, UrlError},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OffsiteRedirect {
+    Follow,
+    Skip,
+    Record,
+}
+
+#[derive(Debug, Clone)]
+pub struct RedirectOptions {
+    pub login_url_pattern: Option<Regex>,
+    pub offsite_redirect: OffsiteRedirect,
+    pub seed: Url,
+    pub scope: Scope,
+    pub extra_hosts: Vec<String>,
+    pub scope_rules: Vec<ScopeRule>,
+    pub max_query_redirects: usize,
+    pub max_redirects: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RedirectAction {
+    Follow,
+    Stop,
+    TooMany,
+    QueryTrap,
+}
+
+pub fn build_policy(options: RedirectOptions) -> Policy {
+    Policy::custom(move |attempt| {
+        match redirect_action(&options, attempt.url(), attempt.previous()) {
+            RedirectAction::Follow => attempt.follow(),
+            RedirectAction::Stop => attempt.stop(),
+            RedirectAction::TooMany => attempt.error("too many redirects"),
+            RedirectAction::QueryTrap => attempt.error("redirect chain only changes the query"),
+        }
+    })
+}
+
+fn redirect_action(
+    options: &RedirectOptions,
+    target: &reqwest::Url,
+    previous: &[reqwest::Url],
+) -> RedirectAction {
+    if let Some(login_url_pattern) = &options.login_url_pattern
+        && login_url_pattern.is_match(target.as_str())
+    {
+        return RedirectAction::Stop;
+    }
+
+    if options.offsite_redirect != OffsiteRedirect::Follow
+        && is_offsite(
+            &options.seed,
+            options.scope,
+            &options.extra_hosts,
+            &options.scope_rules,
+            target,
+        )
+    {
+        return RedirectAction::Stop;
+    }
+
+    if query_only_redirects(target, previous) > options.max_query_redirects {
+        return RedirectAction::QueryTrap;
+    }
+
+    if previous.len() > options.max_redirects {
+        return RedirectAction::TooMany;
+    }
+
+    RedirectAction::Follow
+}
+
+fn query_only_redirects(target: &reqwest::Url, previous: &[reqwest::Url]) -> usize {
+    let same_except_query = |a: &reqwest::Url, b: &reqwest::Url| {
+        a.scheme() == b.scheme()
+            && a.host_str() == b.host_str()
+            && a.port_or_known_default() == b.port_or_known_default()
+            && a.path() == b.path()
+            && a.query() != b.query()
+    };
+
+    previous
+        .iter()
+        .chain([target])
+        .collect::<Vec<_>>()
+        .windows(2)
+        .rev()
+        .take_while(|pair| same_except_query(pair[0], pair[1]))
+        .count()
+}
+
+pub fn is_offsite(
+    seed: &Url,
+    scope: Scope,
+    extra_hosts: &[String],
+    scope_rules: &[ScopeRule],
+    target: &reqwest::Url,
+) -> bool {
+    Url::from_str(target.as_str()).map_or(true, |target| {
+        !in_scope(seed, &target, scope, extra_hosts, scope_rules)
+    })
+}
+
+pub fn final_url(resp: &Response) -> Result<Url, UrlError> {
+    Url::from_str(resp.url().as_str()).map(|url| url.normalize())
+}
+
+pub fn redirect_target(
+    status: StatusCode,
+    request_url: &reqwest::Url,
+    location: Option<&str>,
+) -> Option<reqwest::Url> {
+    if !status.is_redirection() {
+        return None;
+    }
+
+    location.and_then(|location| request_url.join(location).ok())
+}
+
+pub fn is_login_redirect(
+    status: StatusCode,
+    request_url: &reqwest::Url,
+    location: Option<&str>,
+    login_url_pattern: &Regex,
+) -> bool {
+    redirect_target(status, request_url, location)
+        .is_some_and(|target| login_url_pattern.is_match(target.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{response, serve};
+
+    #[test]
+    fn test_login_redirect_classification() {
+        let pattern = Regex::new("/login").unwrap();
+        let request_url = reqwest::Url::parse("https://example.com/private").unwrap();
+
+        assert!(is_login_redirect(
+            StatusCode::FOUND,
+            &request_url,
+            Some("/login?next=/private"),
+            &pattern
+        ));
+        assert!(is_login_redirect(
+            StatusCode::FOUND,
+            &request_url,
+            Some("https://example.com/login"),
+            &pattern
+        ));
+        assert!(!is_login_redirect(
+            StatusCode::FOUND,
+            &request_url,
+            Some("/public"),
+            &pattern
+        ));
+        assert!(!is_login_redirect(
+            StatusCode::OK,
+            &request_url,
+            Some("/login"),
+            &pattern
+        ));
+        assert!(!is_login_redirect(
+            StatusCode::FOUND,
+            &request_url,
+            None,
+            &pattern
+        ));
+    }
+
+    fn options(offsite_redirect: OffsiteRedirect) -> RedirectOptions {
+        RedirectOptions {
+            login_url_pattern: None,
+            offsite_redirect,
+            seed: Url::from_str("https://example.com").unwrap(),
+            scope: Scope::Host,
+            extra_hosts: vec![],
+            scope_rules: vec![],
+            max_query_redirects: 5,
+            max_redirects: 10,
+        }
+    }
+
+    #[test]
+    fn test_offsite_redirect_policies() {
+        let offsite = reqwest::Url::parse("https://elsewhere.com/landing").unwrap();
+        let onsite = reqwest::Url::parse("https://example.com/moved").unwrap();
+
+        let follow = options(OffsiteRedirect::Follow);
+        assert_eq!(
+            redirect_action(&follow, &offsite, &[]),
+            RedirectAction::Follow
+        );
+        assert_eq!(
+            redirect_action(&follow, &onsite, &[]),
+            RedirectAction::Follow
+        );
+
+        let skip = options(OffsiteRedirect::Skip);
+        assert_eq!(redirect_action(&skip, &offsite, &[]), RedirectAction::Stop);
+        assert_eq!(redirect_action(&skip, &onsite, &[]), RedirectAction::Follow);
+
+        let record = options(OffsiteRedirect::Record);
+        assert_eq!(
+            redirect_action(&record, &offsite, &[]),
+            RedirectAction::Stop
+        );
+        assert_eq!(
+            redirect_action(&record, &onsite, &[]),
+            RedirectAction::Follow
+        );
+
+        assert_eq!(
+            redirect_action(&follow, &onsite, &vec![onsite.clone(); 10]),
+            RedirectAction::Follow
+        );
+        assert_eq!(
+            redirect_action(&follow, &onsite, &vec![onsite.clone(); 11]),
+            RedirectAction::TooMany
+        );
+    }
+
+    #[tokio::test]
+    async fn test_final_url_after_redirects() {
+        let address = serve(vec![
+            (
+                "/old",
+                response("301 Moved Permanently", &[("Location", "/older")], ""),
+            ),
+            (
+                "/older",
+                response("302 Found", &[("Location", "/New/?")], ""),
+            ),
+            ("/New/", response("200 OK", &[], "moved")),
+        ])
+        .await;
+
+        let client = reqwest::Client::new();
+        let resp = client.get(format!("{address}/old")).send().await.unwrap();
+        assert_eq!(
+            final_url(&resp).unwrap(),
+            Url::from_str(&format!("{address}/New")).unwrap()
+        );
+
+        let resp = client.get(format!("{address}/New/")).send().await.unwrap();
+        assert_eq!(
+            final_url(&resp).unwrap().to_string(),
+            format!("{address}/New")
+        );
+    }
+
+    #[test]
+    fn test_query_only_redirect_chain_is_a_trap() {
+        let page = |n: usize| reqwest::Url::parse(&format!("https://example.com/list?page={n}"));
+        let chain: Vec<reqwest::Url> = (1..=6).map(|n| page(n).unwrap()).collect();
+        let options = options(OffsiteRedirect::Skip);
+
+        assert_eq!(query_only_redirects(&chain[5], &chain[..5]), 5);
+        assert_eq!(
+            redirect_action(&options, &chain[5], &chain[..5]),
+            RedirectAction::Follow
+        );
+        assert_eq!(
+            redirect_action(&options, &page(7).unwrap(), &chain),
+            RedirectAction::QueryTrap
+        );
+
+        let mut mixed = chain.clone();
+        mixed.insert(
+            3,
+            reqwest::Url::parse("https://example.com/other?page=3").unwrap(),
+        );
+        assert_eq!(query_only_redirects(&page(7).unwrap(), &mixed), 3);
+        assert_eq!(
+            redirect_action(&options, &page(7).unwrap(), &mixed),
+            RedirectAction::Follow
+        );
+
+        let moved = reqwest::Url::parse("https://example.com/a").unwrap();
+        let moved_again = reqwest::Url::parse("https://example.com/b").unwrap();
+        assert_eq!(query_only_redirects(&moved_again, &[moved]), 0);
+    }
+}
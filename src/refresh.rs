@@ -0,0 +1,39 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn is_stale(processed_at: u64, now: u64, refresh_after: Duration) -> bool {
+    now.saturating_sub(processed_at) >= refresh_after.as_secs()
+}
+
+pub fn refresh_in(processed_at: u64, now: u64, refresh_after: Duration) -> Duration {
+    let stale_at = processed_at.saturating_add(refresh_after.as_secs());
+    Duration::from_secs(stale_at.saturating_sub(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_decision() {
+        let refresh_after = Duration::from_secs(3600);
+
+        assert!(!is_stale(10_000, 10_000, refresh_after));
+        assert!(!is_stale(10_000, 13_599, refresh_after));
+        assert!(is_stale(10_000, 13_600, refresh_after));
+        assert!(is_stale(0, 10_000, refresh_after));
+        assert!(!is_stale(10_000, 9_000, refresh_after));
+
+        assert_eq!(
+            refresh_in(10_000, 13_000, refresh_after),
+            Duration::from_secs(600)
+        );
+        assert_eq!(refresh_in(10_000, 20_000, refresh_after), Duration::ZERO);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use reqwest::StatusCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Request,
+    BodyRead,
+    Save,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RetryPolicy {
+    pub request_retries: u32,
+    pub body_read_retries: u32,
+}
+
+impl RetryPolicy {
+    pub fn should_retry(&self, kind: FailureKind, attempts: u32) -> bool {
+        let limit = match kind {
+            FailureKind::Request | FailureKind::Save => self.request_retries,
+            FailureKind::BodyRead => self.body_read_retries,
+        };
+
+        attempts < limit
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserAgentFallback {
+    user_agents: Arc<Vec<String>>,
+    next: Arc<AtomicUsize>,
+    successes: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl UserAgentFallback {
+    pub fn new(user_agents: Vec<String>) -> Self {
+        UserAgentFallback {
+            user_agents: Arc::new(user_agents),
+            next: Arc::new(AtomicUsize::new(0)),
+            successes: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn for_status(&self, status: StatusCode) -> Option<&str> {
+        if status != StatusCode::FORBIDDEN || self.user_agents.is_empty() {
+            return None;
+        }
+
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.user_agents.len();
+        Some(&self.user_agents[index])
+    }
+
+    pub fn record_success(&self, user_agent: &str) {
+        *self
+            .successes
+            .lock()
+            .expect("User agent fallback lock poisoned")
+            .entry(user_agent.to_owned())
+            .or_default() += 1;
+    }
+
+    pub fn successes(&self) -> Vec<(String, usize)> {
+        let mut successes: Vec<(String, usize)> = self
+            .successes
+            .lock()
+            .expect("User agent fallback lock poisoned")
+            .iter()
+            .map(|(user_agent, count)| (user_agent.clone(), *count))
+            .collect();
+        successes.sort_by(|(a_agent, a), (b_agent, b)| b.cmp(a).then_with(|| a_agent.cmp(b_agent)));
+        successes
+    }
+
+    pub fn print_report(&self) {
+        let successes = self.successes();
+        let total: usize = successes.iter().map(|(_, count)| count).sum();
+        println!("Fetched with fallback user agents: {total}");
+        for (user_agent, count) in successes {
+            println!("  {count} {user_agent}");
+        }
+    }
+}
+
+pub fn body_length_failure(content_length: Option<u64>, read: usize) -> Option<FailureKind> {
+    match content_length {
+        Some(expected) if expected != read as u64 => Some(FailureKind::BodyRead),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_read_failure_uses_own_limit() {
+        let policy = RetryPolicy {
+            request_retries: 1,
+            body_read_retries: 3,
+        };
+
+        assert!(policy.should_retry(FailureKind::BodyRead, 0));
+        assert!(policy.should_retry(FailureKind::BodyRead, 2));
+        assert!(!policy.should_retry(FailureKind::BodyRead, 3));
+
+        assert!(policy.should_retry(FailureKind::Request, 0));
+        assert!(!policy.should_retry(FailureKind::Request, 1));
+
+        let no_retries = RetryPolicy::default();
+        assert!(!no_retries.should_retry(FailureKind::BodyRead, 0));
+        assert!(!no_retries.should_retry(FailureKind::Request, 0));
+    }
+
+    #[test]
+    fn test_body_length_mismatch_is_retryable_body_read() {
+        assert_eq!(body_length_failure(None, 10), None);
+        assert_eq!(body_length_failure(Some(10), 10), None);
+        assert_eq!(
+            body_length_failure(Some(10), 4),
+            Some(FailureKind::BodyRead)
+        );
+        assert_eq!(body_length_failure(Some(0), 4), Some(FailureKind::BodyRead));
+
+        let policy = RetryPolicy {
+            request_retries: 0,
+            body_read_retries: 1,
+        };
+        let kind = body_length_failure(Some(10), 4).unwrap();
+        assert!(policy.should_retry(kind, 0));
+    }
+
+    #[test]
+    fn test_user_agent_fallback_on_forbidden() {
+        let fallback = UserAgentFallback::new(vec!["curl/8.0".to_owned(), "Wget/1.21".to_owned()]);
+
+        assert_eq!(fallback.for_status(StatusCode::OK), None);
+        assert_eq!(fallback.for_status(StatusCode::NOT_FOUND), None);
+        assert_eq!(fallback.for_status(StatusCode::FORBIDDEN), Some("curl/8.0"));
+        assert_eq!(
+            fallback.for_status(StatusCode::FORBIDDEN),
+            Some("Wget/1.21")
+        );
+        assert_eq!(fallback.for_status(StatusCode::FORBIDDEN), Some("curl/8.0"));
+
+        let empty = UserAgentFallback::default();
+        assert_eq!(empty.for_status(StatusCode::FORBIDDEN), None);
+
+        fallback.record_success("Wget/1.21");
+        fallback.clone().record_success("curl/8.0");
+        fallback.record_success("Wget/1.21");
+        assert_eq!(
+            fallback.successes(),
+            vec![("Wget/1.21".to_owned(), 2), ("curl/8.0".to_owned(), 1)]
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use reqwest::Client;
+
+use crate::url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    pub async fn fetch(client: &Client, base_url: &Url, user_agent: &str) -> Robots {
+        let robots_url = format!("{}://{}/robots.txt", base_url.scheme, base_url.authority());
+        let body = match client.get(&robots_url).send().await {
+            Ok(resp) if resp.status().is_success() => resp.text().await,
+            Ok(resp) => {
+                eprintln!(
+                    "No robots.txt at {robots_url} ({}), allowing all",
+                    resp.status()
+                );
+                return Robots::default();
+            }
+            Err(err) => Err(err),
+        };
+
+        match body {
+            Ok(body) => Robots::parse(&body, user_agent),
+            Err(err) => {
+                eprintln!("Failed to fetch {robots_url}, allowing all: {err}");
+                Robots::default()
+            }
+        }
+    }
+
+    pub fn parse(body: &str, user_agent: &str) -> Robots {
+        let product = user_agent
+            .split('/')
+            .next()
+            .unwrap_or(user_agent)
+            .trim()
+            .to_lowercase();
+
+        let mut specific = Vec::new();
+        let mut wildcard = Vec::new();
+        let mut specific_delay = None;
+        let mut wildcard_delay = None;
+        let mut named = false;
+        let mut group_agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_agents.clear();
+                        in_rules = false;
+                    }
+                    let agent = value.to_lowercase();
+                    named |= agent == product;
+                    group_agents.push(agent);
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    if value.is_empty() {
+                        continue;
+                    }
+
+                    let rule = Rule {
+                        allow: key == "allow",
+                        pattern: value.to_owned(),
+                    };
+                    if group_agents.contains(&product) {
+                        specific.push(rule);
+                    } else if group_agents.iter().any(|agent| agent == "*") {
+                        wildcard.push(rule);
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    let Some(delay) = value
+                        .parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+                    else {
+                        continue;
+                    };
+
+                    if group_agents.contains(&product) {
+                        specific_delay = Some(delay);
+                    } else if group_agents.iter().any(|agent| agent == "*") {
+                        wildcard_delay = Some(delay);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Robots {
+            rules: if named { specific } else { wildcard },
+            crawl_delay: if named {
+                specific_delay
+            } else {
+                wildcard_delay
+            },
+        }
+    }
+
+    pub fn effective_interval(&self, min_interval: Duration) -> Duration {
+        self.crawl_delay
+            .map_or(min_interval, |delay| delay.max(min_interval))
+    }
+
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = url.request_target();
+
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn allowed(robots: &Robots, path: &str) -> bool {
+        robots.is_allowed(&Url::from_str(&format!("https://example.com{path}")).unwrap())
+    }
+
+    #[test]
+    fn test_longest_matching_rule_wins() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /private\nAllow: /private/public\n\
+             Allow: /shop\nDisallow: /shop/cart\nDisallow: /admin/\n",
+            "yoink/1.0",
+        );
+
+        assert!(allowed(&robots, "/"));
+        assert!(!allowed(&robots, "/private"));
+        assert!(!allowed(&robots, "/private/secret"));
+        assert!(allowed(&robots, "/private/public/page"));
+        assert!(allowed(&robots, "/shop/item"));
+        assert!(!allowed(&robots, "/shop/cart/checkout"));
+        assert!(!allowed(&robots, "/admin/"));
+        assert!(!allowed(&robots, "/admin/users"));
+        assert!(allowed(&robots, "/admin"));
+    }
+
+    #[test]
+    fn test_equal_length_rules_prefer_allow() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /page\nAllow: /page\n", "yoink");
+        assert!(allowed(&robots, "/page"));
+    }
+
+    #[test]
+    fn test_wildcards_and_anchors() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /*.pdf$\nDisallow: /search*q=\nAllow: /search?q=help\n",
+            "yoink",
+        );
+
+        assert!(!allowed(&robots, "/files/report.pdf"));
+        assert!(allowed(&robots, "/files/report.pdf?download=1"));
+        assert!(!allowed(&robots, "/search?lang=en&q=rust"));
+        assert!(allowed(&robots, "/search?q=help"));
+        assert!(allowed(&robots, "/search"));
+    }
+
+    #[test]
+    fn test_specific_user_agent_group_replaces_wildcard() {
+        let body = "User-agent: *\nDisallow: /\n\n# ours\nUser-agent: other\nUser-agent: Yoink\n\
+                    Disallow: /admin\n\nUser-agent: other\nDisallow: /blog\n";
+
+        let robots = Robots::parse(body, "yoink/0.1");
+        assert!(allowed(&robots, "/blog"));
+        assert!(!allowed(&robots, "/admin"));
+
+        let robots = Robots::parse(body, "Mozilla/5.0");
+        assert!(!allowed(&robots, "/blog"));
+        assert!(!allowed(&robots, "/"));
+
+        let body = "User-agent: yoink\nDisallow:\n\nUser-agent: *\nDisallow: /\nCrawl-delay: 3\n";
+        let robots = Robots::parse(body, "yoink/0.1");
+        assert!(allowed(&robots, "/"));
+        assert!(allowed(&robots, "/admin"));
+        assert_eq!(robots.crawl_delay, None);
+    }
+
+    #[test]
+    fn test_crawl_delay() {
+        let body = "User-agent: *\nCrawl-delay: 2\n\nUser-agent: yoink\nCrawl-delay: 0.5\n\n\
+                    User-agent: bad\nCrawl-delay: soon\n";
+        let min_interval = Duration::from_millis(100);
+
+        let robots = Robots::parse(body, "yoink/0.1");
+        assert_eq!(robots.crawl_delay, Some(Duration::from_millis(500)));
+        assert_eq!(
+            robots.effective_interval(min_interval),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            robots.effective_interval(Duration::from_secs(1)),
+            Duration::from_secs(1)
+        );
+
+        let robots = Robots::parse(body, "Mozilla/5.0");
+        assert_eq!(
+            robots.effective_interval(min_interval),
+            Duration::from_secs(2)
+        );
+
+        let robots = Robots::parse(body, "bad");
+        assert_eq!(robots.crawl_delay, None);
+
+        let robots = Robots::parse("User-agent: *\nDisallow: /admin\n", "yoink");
+        assert_eq!(robots.effective_interval(min_interval), min_interval);
+
+        let robots = Robots::parse("User-agent: *\nCrawl-delay: 5\n", "yoink");
+        assert_eq!(
+            robots
+                .effective_interval(Duration::from_millis(100))
+                .as_millis(),
+            5000
+        );
+
+        let robots = Robots::parse("User-agent: *\nCrawl-delay: -1\n", "yoink");
+        assert_eq!(robots.crawl_delay, None);
+    }
+}
@@ -0,0 +1,193 @@
+use std::str::FromStr;
+
+use clap::ValueEnum;
+
+use crate::url::{Url, UrlScheme};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scope {
+    Host,
+    Domain,
+    Prefix,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeRule {
+    scheme: UrlScheme,
+    host: String,
+    port: Option<u16>,
+    path_prefix: Option<String>,
+}
+
+impl FromStr for ScopeRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let url = Url::from_str(value).map_err(|err| format!("{value:?}: {err}"))?;
+        if url.query.is_some() {
+            return Err(format!("{value:?}: scope rules cannot have a query"));
+        }
+
+        Ok(ScopeRule {
+            scheme: url.scheme,
+            host: url.host,
+            port: url.port,
+            path_prefix: url.path,
+        })
+    }
+}
+
+impl ScopeRule {
+    pub fn matches(&self, url: &Url) -> bool {
+        url.scheme == self.scheme
+            && url.host == self.host
+            && url.port == self.port
+            && has_path_prefix(url, self.path_prefix.as_deref())
+    }
+}
+
+fn has_path_prefix(url: &Url, prefix: Option<&str>) -> bool {
+    let prefix = prefix.unwrap_or_default().trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+
+    url.path.as_deref().is_some_and(|path| {
+        path == prefix
+            || path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+pub fn in_scope(
+    seed: &Url,
+    url: &Url,
+    scope: Scope,
+    extra_hosts: &[String],
+    rules: &[ScopeRule],
+) -> bool {
+    if !rules.is_empty() {
+        return rules.iter().any(|rule| rule.matches(url));
+    }
+    if extra_hosts.contains(&url.host) {
+        return true;
+    }
+    if url.scheme != seed.scheme || url.port != seed.port {
+        return false;
+    }
+
+    match scope {
+        Scope::Host => url.host == seed.host,
+        Scope::Domain => {
+            let domain = seed.host.strip_prefix("www.").unwrap_or(&seed.host);
+            url.host == domain || url.host.ends_with(&format!(".{domain}"))
+        }
+        Scope::Prefix => url.host == seed.host && has_path_prefix(url, seed.path.as_deref()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_scope_matrix() {
+        let seed = Url::from_str("https://www.example.com/docs").unwrap();
+        let cases = [
+            ("https://www.example.com/docs", [true, true, true]),
+            ("https://www.example.com/docs/guide", [true, true, true]),
+            ("https://www.example.com/docsearch", [true, true, false]),
+            ("https://www.example.com/blog", [true, true, false]),
+            ("https://www.example.com", [true, true, false]),
+            ("https://example.com/docs", [false, true, false]),
+            ("https://api.example.com/docs", [false, true, false]),
+            ("https://notexample.com/docs", [false, false, false]),
+            ("http://www.example.com/docs", [false, false, false]),
+        ];
+
+        for (url, expected) in cases {
+            let url = Url::from_str(url).unwrap();
+            for (scope, expected) in [Scope::Host, Scope::Domain, Scope::Prefix]
+                .into_iter()
+                .zip(expected)
+            {
+                assert_eq!(
+                    in_scope(&seed, &url, scope, &[], &[]),
+                    expected,
+                    "{url} {scope:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_prefix_scope_with_root_seed() {
+        let seed = Url::from_str("https://example.com").unwrap();
+        let url = Url::from_str("https://example.com/anything").unwrap();
+        assert!(in_scope(&seed, &url, Scope::Prefix, &[], &[]));
+    }
+
+    #[test]
+    fn test_extra_hosts() {
+        let seed = Url::from_str("https://www.example.com/docs").unwrap();
+        let extra_hosts = vec!["assets.example.net".to_owned()];
+        let in_scope = |url: &str, scope| {
+            in_scope(
+                &seed,
+                &Url::from_str(url).unwrap(),
+                scope,
+                &extra_hosts,
+                &[],
+            )
+        };
+
+        for scope in [Scope::Host, Scope::Domain, Scope::Prefix] {
+            assert!(in_scope("https://assets.example.net/app.css", scope));
+            assert!(in_scope("http://assets.example.net/app.css", scope));
+            assert!(!in_scope("https://cdn.example.net/app.css", scope));
+            assert!(!in_scope("https://example.net/app.css", scope));
+            assert!(!in_scope("https://other.com/docs", scope));
+        }
+    }
+
+    #[test]
+    fn test_scope_rules() {
+        let seed = Url::from_str("https://www.example.com/docs").unwrap();
+        let rules: Vec<ScopeRule> = [
+            "https://www.example.com/docs/",
+            "https://api.example.com/v2",
+            "http://legacy.example.com",
+        ]
+        .into_iter()
+        .map(|rule| ScopeRule::from_str(rule).unwrap())
+        .collect();
+        let extra_hosts = vec!["assets.example.net".to_owned()];
+        let in_scope = |url: &str| {
+            in_scope(
+                &seed,
+                &Url::from_str(url).unwrap(),
+                Scope::Domain,
+                &extra_hosts,
+                &rules,
+            )
+        };
+
+        assert!(in_scope("https://www.example.com/docs"));
+        assert!(in_scope("https://www.example.com/docs/guide?page=2"));
+        assert!(!in_scope("https://www.example.com/docsearch"));
+        assert!(!in_scope("https://www.example.com/blog"));
+        assert!(!in_scope("http://www.example.com/docs/guide"));
+        assert!(in_scope("https://api.example.com/v2/users"));
+        assert!(!in_scope("https://api.example.com/v1/users"));
+        assert!(!in_scope("https://api.example.com:8443/v2/users"));
+        assert!(in_scope("http://legacy.example.com/anything"));
+        assert!(!in_scope("https://legacy.example.com/anything"));
+        assert!(!in_scope("https://assets.example.net/app.css"));
+
+        assert!(ScopeRule::from_str("example.com/docs").is_err());
+        assert!(ScopeRule::from_str("https://example.com/search?q=").is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use std::{io::Read, str::FromStr};
+
+use flate2::read::MultiGzDecoder;
+use regex::Regex;
+use reqwest::Client;
+
+use crate::url::Url;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Sitemap {
+    UrlSet(Vec<String>),
+    Index(Vec<String>),
+}
+
+impl Sitemap {
+    fn parse(body: &str) -> Sitemap {
+        let loc = Regex::new(r"(?s)<loc>\s*(?:<!\[CDATA\[)?(.*?)(?:\]\]>)?\s*</loc>")
+            .expect("Invalid loc pattern");
+        let locs = loc
+            .captures_iter(body)
+            .map(|captures| unescape_xml(&captures[1]))
+            .collect();
+
+        if body.contains("<sitemapindex") {
+            Sitemap::Index(locs)
+        } else {
+            Sitemap::UrlSet(locs)
+        }
+    }
+}
+
+pub async fn fetch_sitemap_urls(client: &Client, base_url: &Url) -> Vec<Url> {
+    let sitemap_url = format!("{}://{}/sitemap.xml", base_url.scheme, base_url.authority());
+    let locs = match fetch_sitemap(client, &sitemap_url).await {
+        Ok(Sitemap::UrlSet(locs)) => locs,
+        Ok(Sitemap::Index(children)) => {
+            let mut locs = Vec::new();
+            for child in children {
+                match fetch_sitemap(client, &child).await {
+                    Ok(Sitemap::UrlSet(child_locs)) => locs.extend(child_locs),
+                    Ok(Sitemap::Index(_)) => {
+                        eprintln!("Skipping nested sitemap index {child}");
+                    }
+                    Err(err) => eprintln!("Failed to fetch sitemap {child}: {err}"),
+                }
+            }
+            locs
+        }
+        Err(err) => {
+            eprintln!("No usable sitemap at {sitemap_url}, crawling from the seed only: {err}");
+            return Vec::new();
+        }
+    };
+
+    locs.iter()
+        .filter_map(|loc| Url::from_str(loc).ok())
+        .map(|url| url.normalize())
+        .filter(|url| url.scheme == base_url.scheme && url.same_authority(base_url))
+        .collect()
+}
+
+async fn fetch_sitemap(client: &Client, url: &str) -> Result<Sitemap, String> {
+    let resp = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?;
+    if !resp.status().is_success() {
+        return Err(resp.status().to_string());
+    }
+    let bytes = resp.bytes().await.map_err(|err| err.to_string())?;
+
+    Ok(Sitemap::parse(&decode_body(&bytes)?))
+}
+
+fn decode_body(bytes: &[u8]) -> Result<String, String> {
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(String::from_utf8_lossy(bytes).into_owned());
+    }
+
+    let mut body = String::new();
+    MultiGzDecoder::new(bytes)
+        .read_to_string(&mut body)
+        .map_err(|err| err.to_string())?;
+    Ok(body)
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::*;
+    use crate::test_server::{accept_request, response, serve};
+
+    fn gzip(body: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_parse_sitemap() {
+        let urlset = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://example.com/</loc><lastmod>2024-01-01</lastmod></url>
+              <url><loc>
+                https://example.com/search?q=a&amp;page=2
+              </loc></url>
+              <url><loc><![CDATA[https://example.com/cdata]]></loc></url>
+            </urlset>"#;
+        assert_eq!(
+            Sitemap::parse(urlset),
+            Sitemap::UrlSet(vec![
+                "https://example.com/".to_owned(),
+                "https://example.com/search?q=a&page=2".to_owned(),
+                "https://example.com/cdata".to_owned(),
+            ])
+        );
+
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <sitemap><loc>https://example.com/sitemap-1.xml.gz</loc></sitemap>
+            </sitemapindex>"#;
+        assert_eq!(
+            Sitemap::parse(index),
+            Sitemap::Index(vec!["https://example.com/sitemap-1.xml.gz".to_owned()])
+        );
+
+        assert_eq!(decode_body(&gzip(index)).unwrap(), index);
+        assert!(decode_body(&[0x1f, 0x8b, 0x00]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_sitemap_index() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+        let index = format!(
+            "<sitemapindex><sitemap><loc>{address}/pages.xml.gz</loc></sitemap>\
+             <sitemap><loc>{address}/posts.xml</loc></sitemap>\
+             <sitemap><loc>{address}/missing.xml</loc></sitemap></sitemapindex>"
+        );
+        let pages = gzip(&format!(
+            "<urlset><url><loc>{address}/docs/</loc></url>\
+             <url><loc>https://other.example.com/docs</loc></url></urlset>"
+        ));
+        let posts = format!(
+            "<urlset><url><loc>{address}/posts/1</loc></url>\
+             <url><loc>{}/posts/2</loc></url></urlset>",
+            address.replace("http://", "https://")
+        );
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, request) = accept_request(&listener).await;
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_owned();
+                let resp = match path.as_str() {
+                    "/sitemap.xml" => response("200 OK", &[], &index).into_bytes(),
+                    "/posts.xml" => response("200 OK", &[], &posts).into_bytes(),
+                    "/pages.xml.gz" => {
+                        let mut resp = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            pages.len()
+                        )
+                        .into_bytes();
+                        resp.extend_from_slice(&pages);
+                        resp
+                    }
+                    _ => response("404 Not Found", &[], "").into_bytes(),
+                };
+                let _ = socket.write_all(&resp).await;
+            }
+        });
+
+        let base_url = Url::from_str(&address).unwrap();
+        let urls: Vec<String> = fetch_sitemap_urls(&Client::new(), &base_url)
+            .await
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            urls,
+            vec![format!("{address}/docs"), format!("{address}/posts/1")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_sitemap() {
+        let address = serve(vec![]).await;
+        let base_url = Url::from_str(&address).unwrap();
+
+        assert!(
+            fetch_sitemap_urls(&Client::new(), &base_url)
+                .await
+                .is_empty()
+        );
+    }
+}
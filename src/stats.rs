@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::journal::JournalHistory;
+
+const TOP_FAILED_HOSTS: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub struct JournalStats {
+    pub pending: usize,
+    pub processing: usize,
+    pub processed: usize,
+    pub failed: usize,
+    pub duration_secs: Option<u64>,
+    pub top_failed_hosts: Vec<(String, usize)>,
+}
+
+impl JournalStats {
+    pub fn new(history: &JournalHistory) -> Self {
+        let first = history.processed.iter().map(|(_, at)| *at).min();
+        let last = history.processed.iter().map(|(_, at)| *at).max();
+
+        let mut failed_hosts: HashMap<&str, usize> = HashMap::new();
+        for url in &history.failed {
+            *failed_hosts.entry(&url.host).or_default() += 1;
+        }
+        let mut top_failed_hosts: Vec<(String, usize)> = failed_hosts
+            .into_iter()
+            .map(|(host, count)| (host.to_owned(), count))
+            .collect();
+        top_failed_hosts.sort_by(|(a_host, a), (b_host, b)| b.cmp(a).then(a_host.cmp(b_host)));
+        top_failed_hosts.truncate(TOP_FAILED_HOSTS);
+
+        JournalStats {
+            pending: history.pending.len(),
+            processing: history.processing.len(),
+            processed: history.processed.len(),
+            failed: history.failed.len(),
+            duration_secs: first.zip(last).map(|(first, last)| last - first),
+            top_failed_hosts,
+        }
+    }
+
+    pub fn pages_per_sec(&self) -> Option<f64> {
+        self.duration_secs
+            .filter(|duration| *duration > 0)
+            .map(|duration| self.processed as f64 / duration as f64)
+    }
+
+    pub fn print(&self) {
+        println!(
+            "Pending: {}, processing: {}, processed: {}, failed: {}",
+            self.pending, self.processing, self.processed, self.failed
+        );
+        match self.duration_secs {
+            Some(duration) => println!("Duration: {duration}s"),
+            None => println!("Duration: unknown"),
+        }
+        if let Some(pages_per_sec) = self.pages_per_sec() {
+            println!("Throughput: {pages_per_sec:.2} pages/s");
+        }
+        if !self.top_failed_hosts.is_empty() {
+            println!("Top failed hosts:");
+            for (host, count) in &self.top_failed_hosts {
+                println!("  {host}: {count}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::url::Url;
+
+    fn url(url: &str) -> Url {
+        Url::from_str(url).unwrap()
+    }
+
+    #[test]
+    fn test_journal_stats() {
+        let history = JournalHistory {
+            pending: vec![url("https://example.com/next")],
+            processing: vec![],
+            processed: vec![
+                (url("https://example.com"), 1_000),
+                (url("https://example.com/a"), 1_030),
+                (url("https://example.com/b"), 1_010),
+                (url("https://example.com/c"), 1_040),
+            ],
+            failed: vec![
+                url("https://cdn.example.com/x"),
+                url("https://api.example.com/y"),
+                url("https://cdn.example.com/z"),
+            ],
+            ..JournalHistory::default()
+        };
+
+        let stats = JournalStats::new(&history);
+        assert_eq!(
+            stats,
+            JournalStats {
+                pending: 1,
+                processing: 0,
+                processed: 4,
+                failed: 3,
+                duration_secs: Some(40),
+                top_failed_hosts: vec![
+                    ("cdn.example.com".to_owned(), 2),
+                    ("api.example.com".to_owned(), 1),
+                ],
+            }
+        );
+        assert_eq!(stats.pages_per_sec(), Some(0.1));
+
+        let empty = JournalStats::new(&JournalHistory::default());
+        assert_eq!(empty.duration_secs, None);
+        assert_eq!(empty.pages_per_sec(), None);
+    }
+}
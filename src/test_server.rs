@@ -0,0 +1,53 @@
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+pub async fn accept_request(listener: &TcpListener) -> (TcpStream, String) {
+    let (mut socket, _) = listener.accept().await.unwrap();
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = socket.read(&mut buf).await.unwrap();
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    (socket, String::from_utf8_lossy(&request).into_owned())
+}
+
+pub fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n{body}",
+        body.len()
+    )
+}
+
+pub async fn serve(routes: Vec<(&'static str, String)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, request) = accept_request(&listener).await;
+            let path = request.split_whitespace().nth(1).unwrap_or("/").to_owned();
+            let resp = routes
+                .iter()
+                .find(|(route, _)| *route == path)
+                .map(|(_, resp)| resp.to_owned())
+                .unwrap_or_else(|| response("404 Not Found", &[], ""));
+
+            let _ = socket.write_all(resp.as_bytes()).await;
+        }
+    });
+
+    address
+}
@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use crate::url::Url;
+
+const LARGE_CONTENT_LENGTH: u64 = 1024 * 1024;
+const LARGE_EXTENSIONS: [&str; 20] = [
+    "7z", "avi", "bz2", "dmg", "exe", "flac", "gz", "iso", "mkv", "mov", "mp3", "mp4", "msi",
+    "pdf", "rar", "tar", "tgz", "wav", "xz", "zip",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestTimeouts {
+    pub default: Duration,
+    pub large: Duration,
+}
+
+impl RequestTimeouts {
+    pub fn for_url(&self, url: &Url) -> Duration {
+        self.select(has_large_extension(url))
+    }
+
+    pub fn for_body(&self, url: &Url, content_length: Option<u64>) -> Duration {
+        self.select(
+            has_large_extension(url)
+                || content_length.is_some_and(|length| length >= LARGE_CONTENT_LENGTH),
+        )
+    }
+
+    pub fn longest(&self) -> Duration {
+        self.default.max(self.large)
+    }
+
+    fn select(&self, large: bool) -> Duration {
+        if large { self.large } else { self.default }
+    }
+}
+
+fn has_large_extension(url: &Url) -> bool {
+    let Some(path) = &url.path else {
+        return false;
+    };
+    let name = path.rsplit('/').next().unwrap_or_default();
+
+    name.rsplit_once('.').is_some_and(|(_, extension)| {
+        LARGE_EXTENSIONS
+            .iter()
+            .any(|large| large.eq_ignore_ascii_case(extension))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn test_timeout_by_expected_size() {
+        let timeouts = RequestTimeouts {
+            default: Duration::from_secs(1),
+            large: Duration::from_secs(60),
+        };
+        let url = |url: &str| Url::from_str(url).unwrap();
+
+        assert_eq!(
+            timeouts.for_url(&url("https://example.com/docs/page.html")),
+            timeouts.default
+        );
+        assert_eq!(
+            timeouts.for_url(&url("https://example.com/files/Release.ZIP")),
+            timeouts.large
+        );
+        assert_eq!(
+            timeouts.for_url(&url("https://example.com/files.zip/readme")),
+            timeouts.default
+        );
+        assert_eq!(
+            timeouts.for_url(&url("https://example.com")),
+            timeouts.default
+        );
+
+        let page = url("https://example.com/download");
+        assert_eq!(timeouts.for_body(&page, None), timeouts.default);
+        assert_eq!(timeouts.for_body(&page, Some(4096)), timeouts.default);
+        assert_eq!(
+            timeouts.for_body(&page, Some(LARGE_CONTENT_LENGTH)),
+            timeouts.large
+        );
+        assert_eq!(
+            timeouts.for_body(&url("https://example.com/a.pdf"), Some(0)),
+            timeouts.large
+        );
+    }
+}
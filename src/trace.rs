@@ -0,0 +1,115 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use reqwest::StatusCode;
+
+use crate::url::Url;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceEvent {
+    Enqueued { from: Url },
+    Deduplicated { from: Url },
+    OutOfScope { from: Url },
+    Excluded { from: Url },
+    DisallowedByRobots { from: Url },
+    TooDeep { from: Url, depth: u32 },
+    HostLimitReached { from: Url },
+    HostBudgetExhausted,
+    HostSampleFull,
+    Dispatched { depth: u32 },
+    RequestFailed,
+    Retried { attempt: u32 },
+    Fetched { status: StatusCode },
+    UserAgentSwitched { user_agent: String },
+    StatusFromBody { status: u16 },
+    DomTooLarge { nodes: usize },
+    TooSmall { bytes: usize },
+    UnwantedContentType { content_type: String },
+    AuthRequired,
+    OffsiteRedirect,
+    Redirected { to: Url },
+    BodyReadFailed,
+    DuplicateContent { of: Url },
+    DuplicateTitle { of: Url },
+    Saved,
+    SaveFailed,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceEvent::Enqueued { from } => write!(f, "enqueued from {from}"),
+            TraceEvent::Deduplicated { from } => write!(f, "deduplicated from {from}"),
+            TraceEvent::OutOfScope { from } => write!(f, "out of scope from {from}"),
+            TraceEvent::Excluded { from } => write!(f, "excluded by url filter from {from}"),
+            TraceEvent::DisallowedByRobots { from } => {
+                write!(f, "disallowed by robots.txt from {from}")
+            }
+            TraceEvent::TooDeep { from, depth } => {
+                write!(f, "beyond max depth ({depth}) from {from}")
+            }
+            TraceEvent::HostLimitReached { from } => {
+                write!(f, "new host beyond max hosts from {from}")
+            }
+            TraceEvent::HostBudgetExhausted => write!(f, "host time budget exhausted"),
+            TraceEvent::HostSampleFull => write!(f, "host page sample is full"),
+            TraceEvent::Dispatched { depth } => write!(f, "dispatched at depth {depth}"),
+            TraceEvent::RequestFailed => write!(f, "request failed"),
+            TraceEvent::Retried { attempt } => write!(f, "retried (attempt {attempt})"),
+            TraceEvent::Fetched { status } => write!(f, "fetched with status {status}"),
+            TraceEvent::UserAgentSwitched { user_agent } => {
+                write!(f, "retried with user agent {user_agent}")
+            }
+            TraceEvent::StatusFromBody { status } => write!(f, "body reports status {status}"),
+            TraceEvent::DomTooLarge { nodes } => {
+                write!(f, "document has {nodes} nodes, skipped link extraction")
+            }
+            TraceEvent::TooSmall { bytes } => write!(f, "body is {bytes} bytes, not saved"),
+            TraceEvent::UnwantedContentType { content_type } => {
+                write!(f, "content type {content_type} not saved")
+            }
+            TraceEvent::AuthRequired => write!(f, "authentication required"),
+            TraceEvent::OffsiteRedirect => write!(f, "redirected off-site"),
+            TraceEvent::Redirected { to } => write!(f, "redirected to {to}"),
+            TraceEvent::BodyReadFailed => write!(f, "body read failed"),
+            TraceEvent::DuplicateContent { of } => write!(f, "same content as {of}, not saved"),
+            TraceEvent::DuplicateTitle { of } => write!(f, "same title as {of}, not saved"),
+            TraceEvent::Saved => write!(f, "saved"),
+            TraceEvent::SaveFailed => write!(f, "save failed"),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Tracer {
+    url: Option<Url>,
+    events: Arc<Mutex<Vec<TraceEvent>>>,
+}
+
+impl Tracer {
+    pub fn new(url: Option<Url>) -> Self {
+        Tracer {
+            url,
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn trace(&self, url: &Url, event: TraceEvent) {
+        if self.url.as_ref() != Some(url) {
+            return;
+        }
+
+        eprintln!("[trace] {url}: {event}");
+        self.events
+            .lock()
+            .expect("Trace events lock poisoned")
+            .push(event);
+    }
+
+    #[cfg(test)]
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.events.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,48 @@
+pub struct Watchdog {
+    max_empty_polls: Option<u32>,
+    empty_polls: u32,
+}
+
+impl Watchdog {
+    pub fn new(max_empty_polls: Option<u32>) -> Self {
+        Watchdog {
+            max_empty_polls,
+            empty_polls: 0,
+        }
+    }
+
+    pub fn record(&mut self, progressed: bool) -> bool {
+        if progressed {
+            self.empty_polls = 0;
+            return false;
+        }
+
+        self.empty_polls += 1;
+        self.max_empty_polls
+            .is_some_and(|max_empty_polls| self.empty_polls >= max_empty_polls)
+    }
+
+    pub fn empty_polls(&self) -> u32 {
+        self.empty_polls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchdog_trips_without_progress() {
+        let mut watchdog = Watchdog::new(Some(3));
+        assert!(!watchdog.record(false));
+        assert!(!watchdog.record(false));
+        assert!(!watchdog.record(true));
+        assert!(!watchdog.record(false));
+        assert!(!watchdog.record(false));
+        assert!(watchdog.record(false));
+        assert_eq!(watchdog.empty_polls(), 3);
+
+        let mut disabled = Watchdog::new(None);
+        assert!((0..100).all(|_| !disabled.record(false)));
+    }
+}